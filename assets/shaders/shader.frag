@@ -1,58 +1,202 @@
 #version 460 core
 
-layout (std140, binding = 1) uniform Light
+struct Light
 {
-    vec3 lightPos;
+    // xyz: position, w: 0 for directional, 1 for point and 2 for spot lights
+    vec4 position;
+    // xyz: direction the light shines to, w: range, 0 when infinite
+    vec4 direction;
+    // rgb: color multiplied by the intensity
+    vec4 color;
+    // x: scale, y: offset turning the cosine of the angle to the spot direction into the
+    // cone attenuation
+    vec4 cone;
+};
+
+// Must match MAX_LIGHTS in light.rs
+layout (std140, binding = 1) uniform Lights
+{
+    Light lights[8];
+    int lightCount;
 };
 
 in vec3 FragPos;
 in vec2 TexCoord;
+in vec2 TexCoord1;
+in vec4 VertexColor;
 in mat3 TBN;
 
-uniform sampler2D diffuseTexture;
+uniform sampler2D baseColorTexture;
+uniform sampler2D metallicRoughnessTexture;
 uniform sampler2D normalTexture;
+uniform sampler2D occlusionTexture;
+uniform sampler2D emissiveTexture;
+
+// Texture coordinate set and KHR_texture_transform matrix of every texture
+uniform int baseColorUvSet;
+uniform int metallicRoughnessUvSet;
+uniform int normalUvSet;
+uniform int occlusionUvSet;
+uniform int emissiveUvSet;
+uniform mat3 baseColorUvTransform;
+uniform mat3 metallicRoughnessUvTransform;
+uniform mat3 normalUvTransform;
+uniform mat3 occlusionUvTransform;
+uniform mat3 emissiveUvTransform;
+
+// Material properties
+uniform vec4 baseColorFactor;
+uniform float metallicFactor;
+uniform float roughnessFactor;
+uniform float normalScale;
+uniform float occlusionStrength;
+uniform vec3 emissiveFactor;
+
+// 0: opaque, 1: mask, 2: blend
+uniform int alphaMode;
+uniform float alphaCutoff;
 
 uniform vec3 viewPos;
 
 out vec4 FragColor;
-  
+
+const float PI = 3.14159265359;
+
+// Trowbridge-Reitz GGX normal distribution
+float distributionGGX(float NdotH, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = NdotH * NdotH * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * denom * denom);
+}
+
+// Smith's method with Schlick-GGX for both view and light directions
+float geometrySmith(float NdotV, float NdotL, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+
+    float ggxV = NdotV / (NdotV * (1.0 - k) + k);
+    float ggxL = NdotL / (NdotL * (1.0 - k) + k);
+
+    return ggxV * ggxL;
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec2 textureUv(int uvSet, mat3 uvTransform)
+{
+    vec2 uv = uvSet == 1 ? TexCoord1 : TexCoord;
+    return (uvTransform * vec3(uv, 1.0)).xy;
+}
+
+// Direction towards the light and the light reaching the fragment, following the
+// KHR_lights_punctual attenuation
+void incomingLight(Light light, out vec3 lightDir, out vec3 radiance)
+{
+    int type = int(light.position.w);
+
+    if (type == 0) {
+        lightDir = -normalize(light.direction.xyz);
+        radiance = light.color.rgb;
+        return;
+    }
+
+    vec3 toLight = light.position.xyz - FragPos;
+    float distance = length(toLight);
+    lightDir = toLight / distance;
+
+    float attenuation = 1.0 / max(distance * distance, 0.0001);
+
+    float range = light.direction.w;
+    if (range > 0.0) {
+        attenuation *= pow(clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0), 2.0);
+    }
+
+    if (type == 2) {
+        float cosAngle = dot(normalize(light.direction.xyz), -lightDir);
+        float cone = clamp(cosAngle * light.cone.x + light.cone.y, 0.0, 1.0);
+        attenuation *= cone * cone;
+    }
+
+    radiance = light.color.rgb * attenuation;
+}
+
 void main()
 {
-    vec3 base_color = vec3(texture(diffuseTexture, TexCoord));
+    vec2 baseColorUv = textureUv(baseColorUvSet, baseColorUvTransform);
+    vec4 base_color = texture(baseColorTexture, baseColorUv) * baseColorFactor * VertexColor;
+
+    if (alphaMode == 1 && base_color.a < alphaCutoff) {
+        discard;
+    }
+    float alpha = alphaMode == 2 ? base_color.a : 1.0;
 
-    // Material properties
-    float ambient_coef = 0.5;
-    vec3 diffuse_coef = vec3(0.7, 0.7, 0.7);
-    vec3 specular_coef = vec3(0.5, 0.5, 0.5);
-    float shininnes_coef = 32.0;
+    // Metalness is stored in the blue channel and roughness in the green channel
+    vec2 metallicRoughnessUv = textureUv(metallicRoughnessUvSet, metallicRoughnessUvTransform);
+    vec4 metallic_roughness = texture(metallicRoughnessTexture, metallicRoughnessUv);
+    float metallic = metallicFactor * metallic_roughness.b;
+    float roughness = clamp(roughnessFactor * metallic_roughness.g, 0.04, 1.0);
 
-    // Light properties
-    vec3 lightColor = vec3(1.0, 1.0, 1.0);
+    vec2 occlusionUv = textureUv(occlusionUvSet, occlusionUvTransform);
+    float occlusion = 1.0 + occlusionStrength * (texture(occlusionTexture, occlusionUv).r - 1.0);
 
-    // Ambient light
-    vec3 ambient = base_color * ambient_coef;
+    vec2 emissiveUv = textureUv(emissiveUvSet, emissiveUvTransform);
+    vec3 emissive = emissiveFactor * texture(emissiveTexture, emissiveUv).rgb;
+
+    vec3 ambientColor = vec3(0.03);
 
     // Obtain normal from normal map in range [0,1]
-    vec3 normal = texture(normalTexture, TexCoord).rgb;
-    // Transform normal vector to range [-1,1] 
+    vec3 normal = texture(normalTexture, textureUv(normalUvSet, normalUvTransform)).rgb;
+    // Transform normal vector to range [-1,1] and apply the normal scale
     normal = (normal * 2.0) - 1.0;
+    normal.xy *= normalScale;
     // Transform normal to TBN space
     normal = normalize(TBN * normal);
-    
-    vec3 lightDir = normalize(lightPos - FragPos); 
-
-    // Diffuse 
-    float diff = max(dot(normal, lightDir), 0.0);
-    vec3 diffuse = diff * base_color;
+    // Back faces of double sided materials are lit from their own side
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
 
-    // specular
     vec3 viewDir = normalize(viewPos - FragPos);
-    vec3 reflectDir = reflect(-lightDir, normal);  
-    vec3 halfwayDir = normalize(lightDir + viewDir);  
-    float spec = pow(max(dot(normal, halfwayDir), 0.0), shininnes_coef);
-    vec3 specular = specular_coef * spec;
+    float NdotV = max(dot(normal, viewDir), 0.0001);
+
+    // Dielectrics reflect 4% of the light at normal incidence, metals tint it with their color
+    vec3 F0 = mix(vec3(0.04), base_color.rgb, metallic);
+
+    vec3 direct = vec3(0.0);
+    for (int i = 0; i < lightCount; i++) {
+        vec3 lightDir;
+        vec3 radiance;
+        incomingLight(lights[i], lightDir, radiance);
+
+        vec3 halfwayDir = normalize(lightDir + viewDir);
+
+        float NdotL = max(dot(normal, lightDir), 0.0);
+        float NdotH = max(dot(normal, halfwayDir), 0.0);
+        float HdotV = max(dot(halfwayDir, viewDir), 0.0);
+
+        // Cook-Torrance specular BRDF
+        float D = distributionGGX(NdotH, roughness);
+        float G = geometrySmith(NdotV, NdotL, roughness);
+        vec3 F = fresnelSchlick(HdotV, F0);
+        vec3 specular = (D * G * F) / (4.0 * NdotV * max(NdotL, 0.0001));
+
+        // Lambertian diffuse for the light that is not reflected, metals have no diffuse
+        vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+        vec3 diffuse = kD * base_color.rgb / PI;
+
+        direct += (diffuse + specular) * radiance * NdotL;
+    }
+
+    vec3 ambient = ambientColor * base_color.rgb * occlusion;
 
-    vec3 result = ambient + diffuse + specular;
+    vec3 result = ambient + direct + emissive;
 
-    FragColor = vec4(result * lightColor, 1.0);
+    FragColor = vec4(result, alpha);
 }
@@ -5,28 +5,79 @@ layout (location = 1) in vec2 aTexCoord;
 layout (location = 2) in vec3 aNorm;
 layout (location = 3) in vec3 aTangent;
 layout (location = 4) in vec3 aBitangent;
+layout (location = 5) in uvec4 aJoints;
+layout (location = 6) in vec4 aWeights;
+layout (location = 7) in vec4 aColor;
+layout (location = 8) in vec2 aTexCoord1;
 
 layout (std140, binding = 0) uniform Matrices
 {
     mat4 projection;
     mat4 view;
-    mat4 model;
-    mat4 normalMatrix;
 };
 
+// Must match MAX_JOINTS in model.rs
+layout (std140, binding = 2) uniform JointMatrices
+{
+    mat4 jointMatrices[128];
+};
+
+// Position, normal and tangent displacements of every morph target, stored per vertex
+uniform samplerBuffer morphTargets;
+uniform int morphTargetCount;
+// Must match MAX_MORPH_TARGETS in model.rs
+uniform float morphWeights[64];
+
+uniform mat4 model;
+uniform mat4 normalMatrix;
+uniform bool skinned;
+
 out vec3 FragPos;
 out vec2 TexCoord;
+out vec2 TexCoord1;
+out vec4 VertexColor;
 out mat3 TBN;
   
 void main()
 {
-    vec3 T = normalize(vec3(normalMatrix * vec4(aTangent, 0.0)));
-    vec3 B = normalize(vec3(normalMatrix * vec4(aBitangent, 0.0)));
-    vec3 N = normalize(vec3(normalMatrix * vec4(aNorm, 0.0)));
+    // Add the weighted displacements of the morph targets
+    vec3 morphedPos = aPos;
+    vec3 morphedNorm = aNorm;
+    vec3 morphedTangent = aTangent;
+    vec3 morphedBitangent = aBitangent;
+    if (morphTargetCount > 0) {
+        for (int i = 0; i < morphTargetCount; i++) {
+            int texel = (gl_VertexID * morphTargetCount + i) * 3;
+            morphedPos += morphWeights[i] * texelFetch(morphTargets, texel).xyz;
+            morphedNorm += morphWeights[i] * texelFetch(morphTargets, texel + 1).xyz;
+            morphedTangent += morphWeights[i] * texelFetch(morphTargets, texel + 2).xyz;
+        }
+
+        // Rebuild the bitangent with the handedness of the original tangent frame
+        float handedness = sign(dot(cross(aNorm, aTangent), aBitangent));
+        morphedBitangent = cross(morphedNorm, morphedTangent) * handedness;
+    }
+
+    // Blend the transforms of the joints influencing the vertex
+    mat4 skinMatrix = mat4(1.0);
+    if (skinned) {
+        skinMatrix = aWeights.x * jointMatrices[aJoints.x]
+            + aWeights.y * jointMatrices[aJoints.y]
+            + aWeights.z * jointMatrices[aJoints.z]
+            + aWeights.w * jointMatrices[aJoints.w];
+    }
+
+    vec4 position = skinMatrix * vec4(morphedPos, 1.0);
+
+    vec3 T = normalize(vec3(normalMatrix * skinMatrix * vec4(morphedTangent, 0.0)));
+    vec3 B = normalize(vec3(normalMatrix * skinMatrix * vec4(morphedBitangent, 0.0)));
+    vec3 N = normalize(vec3(normalMatrix * skinMatrix * vec4(morphedNorm, 0.0)));
 
     TexCoord = aTexCoord;
-    FragPos = vec3(model * vec4(aPos, 1.0));
+    TexCoord1 = aTexCoord1;
+    VertexColor = aColor;
+    FragPos = vec3(model * position);
     TBN = mat3(T, B, N);
 
-    gl_Position = projection * view * model * vec4(aPos, 1.0); 
+    gl_Position = projection * view * model * position;
 }
\ No newline at end of file
@@ -0,0 +1,254 @@
+mod player;
+
+use cgmath::{InnerSpace, Quaternion, Vector3, VectorSpace};
+
+pub use player::AnimationPlayer;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// Cubic Hermite spline, where every keyframe stores an in tangent, a value and an out
+    /// tangent, in that order.
+    CubicSpline,
+}
+
+/// Values of an animated property, one per keyframe or three per keyframe for cubic splines.
+#[derive(Clone)]
+pub enum Keyframes {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+    MorphWeights(Vec<Vec<f32>>),
+}
+
+impl Keyframes {
+    pub fn len(&self) -> usize {
+        match self {
+            Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+            Keyframes::Rotation(values) => values.len(),
+            Keyframes::MorphWeights(values) => values.len(),
+        }
+    }
+}
+
+/// Local transform and morph target weights of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodePose {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+    pub weights: Vec<f32>,
+}
+
+impl NodePose {
+    /// Blends towards `other`, where an `amount` of 0 keeps this pose and 1 returns `other`.
+    pub fn blend(&self, other: &NodePose, amount: f32) -> NodePose {
+        NodePose {
+            translation: self.translation.lerp(other.translation, amount),
+            rotation: self.rotation.slerp(other.rotation, amount),
+            scale: self.scale.lerp(other.scale, amount),
+            weights: match self.weights.len() == other.weights.len() {
+                true => self.weights.interpolate(&other.weights, amount),
+                false => other.weights.clone(),
+            },
+        }
+    }
+}
+
+/// Animates one property of a node.
+#[derive(Clone)]
+pub struct Channel {
+    node: usize,
+    interpolation: Interpolation,
+    times: Vec<f32>,
+    keyframes: Keyframes,
+}
+
+impl Channel {
+    pub fn new(
+        node: usize,
+        interpolation: Interpolation,
+        times: Vec<f32>,
+        keyframes: Keyframes,
+    ) -> Channel {
+        Channel {
+            node,
+            interpolation,
+            times,
+            keyframes,
+        }
+    }
+
+    pub fn node(&self) -> usize {
+        self.node
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn times(&self) -> &[f32] {
+        &self.times
+    }
+
+    pub fn keyframes(&self) -> &Keyframes {
+        &self.keyframes
+    }
+
+    fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    /// Writes the value of the animated property at `time` into the pose of the node.
+    fn apply(&self, time: f32, poses: &mut [NodePose]) {
+        let Some(pose) = poses.get_mut(self.node) else {
+            return;
+        };
+
+        let (times, interpolation) = (&self.times, self.interpolation);
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                pose.translation = sample(times, values, interpolation, time)
+            }
+            Keyframes::Rotation(values) => {
+                pose.rotation = sample(times, values, interpolation, time).normalize()
+            }
+            Keyframes::Scale(values) => pose.scale = sample(times, values, interpolation, time),
+            Keyframes::MorphWeights(values) => {
+                pose.weights = sample(times, values, interpolation, time)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl Animation {
+    pub fn new(channels: Vec<Channel>) -> Animation {
+        let duration = channels
+            .iter()
+            .map(|channel| channel.duration())
+            .fold(0.0, f32::max);
+
+        Animation { channels, duration }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Overwrites the animated properties of the poses, indexed by node, with their values at
+    /// `time`. Properties without a channel keep their value.
+    pub fn sample(&self, time: f32, poses: &mut [NodePose]) {
+        for channel in &self.channels {
+            channel.apply(time, poses);
+        }
+    }
+}
+
+fn sample<T: Keyframe>(times: &[f32], values: &[T], interpolation: Interpolation, time: f32) -> T {
+    let value = |index: usize| match interpolation {
+        Interpolation::CubicSpline => &values[index * 3 + 1],
+        _ => &values[index],
+    };
+
+    // Index of the first keyframe after `time`
+    let next = times.partition_point(|keyframe_time| *keyframe_time <= time);
+
+    if next == 0 {
+        return value(0).clone();
+    }
+    if next == times.len() {
+        return value(times.len() - 1).clone();
+    }
+
+    let previous = next - 1;
+    let delta = times[next] - times[previous];
+    let amount = (time - times[previous]) / delta;
+
+    match interpolation {
+        Interpolation::Step => value(previous).clone(),
+        Interpolation::Linear => value(previous).interpolate(value(next), amount),
+        Interpolation::CubicSpline => {
+            let out_tangent = &values[previous * 3 + 2];
+            let in_tangent = &values[next * 3];
+
+            T::hermite(
+                [value(previous), out_tangent, value(next), in_tangent],
+                amount,
+                delta,
+            )
+        }
+    }
+}
+
+/// Hermite basis functions at `t`, for the start value, start tangent, end value and end
+/// tangent respectively.
+fn hermite_basis(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    [
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        t3 - 2.0 * t2 + t,
+        -2.0 * t3 + 3.0 * t2,
+        t3 - t2,
+    ]
+}
+
+trait Keyframe: Clone {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self;
+
+    /// Evaluates the spline between two keyframes given as `[start value, start out tangent,
+    /// end value, end in tangent]`, with tangents scaled by the time between the keyframes.
+    fn hermite(points: [&Self; 4], amount: f32, delta: f32) -> Self;
+}
+
+impl Keyframe for Vector3<f32> {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self {
+        self.lerp(*other, amount)
+    }
+
+    fn hermite([p0, m0, p1, m1]: [&Self; 4], amount: f32, delta: f32) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(amount);
+        p0 * h00 + m0 * (h10 * delta) + p1 * h01 + m1 * (h11 * delta)
+    }
+}
+
+impl Keyframe for Quaternion<f32> {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self {
+        self.slerp(*other, amount)
+    }
+
+    fn hermite([p0, m0, p1, m1]: [&Self; 4], amount: f32, delta: f32) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(amount);
+        (p0 * h00 + m0 * (h10 * delta) + p1 * h01 + m1 * (h11 * delta)).normalize()
+    }
+}
+
+impl Keyframe for Vec<f32> {
+    fn interpolate(&self, other: &Self, amount: f32) -> Self {
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| a + (b - a) * amount)
+            .collect()
+    }
+
+    fn hermite([p0, m0, p1, m1]: [&Self; 4], amount: f32, delta: f32) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(amount);
+
+        (0..p0.len())
+            .map(|i| p0[i] * h00 + m0[i] * (h10 * delta) + p1[i] * h01 + m1[i] * (h11 * delta))
+            .collect()
+    }
+}
@@ -0,0 +1,164 @@
+use crate::model::Model;
+
+use super::{Animation, NodePose};
+
+#[derive(Clone, Copy)]
+struct ClipState {
+    animation: usize,
+    time: f32,
+}
+
+impl ClipState {
+    fn sample(&self, animations: &[Animation], rest_pose: &[NodePose]) -> Vec<NodePose> {
+        let mut pose = rest_pose.to_vec();
+        if let Some(animation) = animations.get(self.animation) {
+            animation.sample(self.time, &mut pose);
+        }
+        pose
+    }
+}
+
+/// Clip being faded out while the current clip fades in.
+struct CrossFade {
+    from: ClipState,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Plays the animations of a model, by index, and blends between them when switching clips.
+pub struct AnimationPlayer {
+    clip: Option<ClipState>,
+    cross_fade: Option<CrossFade>,
+    playing: bool,
+    looping: bool,
+    speed: f32,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        AnimationPlayer::new()
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer {
+            clip: None,
+            cross_fade: None,
+            playing: false,
+            looping: true,
+            speed: 1.0,
+        }
+    }
+
+    /// Starts playing an animation from the beginning, replacing the current one at once.
+    pub fn play(&mut self, animation: usize) {
+        self.clip = Some(ClipState {
+            animation,
+            time: 0.0,
+        });
+        self.cross_fade = None;
+        self.playing = true;
+    }
+
+    /// Starts playing an animation from the beginning, blending it in over `duration` seconds
+    /// while the current animation keeps playing underneath.
+    pub fn cross_fade(&mut self, animation: usize, duration: f32) {
+        let Some(from) = self.clip.filter(|_| duration > 0.0) else {
+            self.play(animation);
+            return;
+        };
+
+        self.play(animation);
+        self.cross_fade = Some(CrossFade {
+            from,
+            elapsed: 0.0,
+            duration,
+        });
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = self.clip.is_some();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_animation(&self) -> Option<usize> {
+        self.clip.map(|clip| clip.animation)
+    }
+
+    /// Whether animations wrap around when reaching their end, otherwise they hold the last
+    /// frame.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Playback rate, negative values play animations backwards.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Advances playback by `delta_time` seconds and poses the nodes of the model.
+    pub fn update(&mut self, delta_time: f32, model: &mut Model) {
+        if !self.playing {
+            return;
+        }
+        let Some(clip) = &mut self.clip else {
+            return;
+        };
+
+        let animations = model.animations();
+        let (delta, looping) = (delta_time * self.speed, self.looping);
+
+        advance(clip, animations, delta, looping);
+
+        let rest_pose = model.pose();
+        let mut pose = clip.sample(animations, &rest_pose);
+
+        if let Some(cross_fade) = &mut self.cross_fade {
+            advance(&mut cross_fade.from, animations, delta, looping);
+            cross_fade.elapsed += delta_time;
+
+            let amount = (cross_fade.elapsed / cross_fade.duration).min(1.0);
+            let from_pose = cross_fade.from.sample(animations, &rest_pose);
+
+            pose = from_pose
+                .iter()
+                .zip(&pose)
+                .map(|(from, to)| from.blend(to, amount))
+                .collect();
+
+            if amount >= 1.0 {
+                self.cross_fade = None;
+            }
+        }
+
+        model.set_pose(pose);
+    }
+}
+
+fn advance(clip: &mut ClipState, animations: &[Animation], delta: f32, looping: bool) {
+    let duration = animations
+        .get(clip.animation)
+        .map_or(0.0, |animation| animation.duration());
+
+    let time = clip.time + delta;
+    clip.time = match looping && duration > 0.0 {
+        true => time.rem_euclid(duration),
+        false => time.clamp(0.0, duration),
+    };
+}
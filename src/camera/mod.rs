@@ -1,8 +1,66 @@
 mod camera_controller;
-use cgmath::{perspective, Angle, Deg, Matrix4, Point3, Rad, Vector3};
+use cgmath::{
+    ortho, perspective, Angle, Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Transform,
+    Vector3, Vector4,
+};
 
 pub use camera_controller::CameraController;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        yfov: Rad<f32>,
+        /// Aspect ratio of the field of view, `None` uses the aspect ratio of the viewport.
+        aspect: Option<f32>,
+        near: f32,
+        /// Far clipping plane, `None` uses an infinite projection.
+        far: Option<f32>,
+    },
+    Orthographic {
+        /// Half of the width of the view volume.
+        xmag: f32,
+        /// Half of the height of the view volume.
+        ymag: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+impl Projection {
+    pub fn matrix(&self, viewport_aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective {
+                yfov,
+                aspect,
+                near,
+                far: Some(far),
+            } => perspective(yfov, aspect.unwrap_or(viewport_aspect), near, far),
+            Projection::Perspective {
+                yfov,
+                aspect,
+                near,
+                far: None,
+            } => {
+                let focal_length = 1.0 / (yfov / 2.0).tan();
+                let aspect = aspect.unwrap_or(viewport_aspect);
+
+                Matrix4::from_cols(
+                    Vector4::new(focal_length / aspect, 0.0, 0.0, 0.0),
+                    Vector4::new(0.0, focal_length, 0.0, 0.0),
+                    Vector4::new(0.0, 0.0, -1.0, -1.0),
+                    Vector4::new(0.0, 0.0, -2.0 * near, 0.0),
+                )
+            }
+            Projection::Orthographic {
+                xmag,
+                ymag,
+                near,
+                far,
+            } => ortho(-xmag, xmag, -ymag, ymag, near, far),
+        }
+    }
+}
+
 pub struct Camera {
     pub(self) position: Point3<f32>,
     pub(self) yaw: Rad<f32>,
@@ -13,10 +71,9 @@ pub struct Camera {
     pub(self) up: Vector3<f32>,
     pub(self) forward: Vector3<f32>,
 
-    fovy: f32,
+    projection: Projection,
+    /// Aspect ratio of the viewport.
     aspect: f32,
-    near: f32,
-    far: f32,
 }
 
 impl Camera {
@@ -44,10 +101,39 @@ impl Camera {
             right,
             up,
             forward,
-            fovy,
+            projection: Projection::Perspective {
+                yfov: Deg(fovy).into(),
+                aspect: None,
+                near,
+                far: Some(far),
+            },
+            aspect,
+        }
+    }
+
+    /// Creates a camera placed by a world transform, looking down its -Z axis. Any roll
+    /// around the view direction is dropped, since the camera always keeps +Y up.
+    pub fn from_transform(transform: Matrix4<f32>, projection: Projection, aspect: f32) -> Camera {
+        let position = transform.transform_point(Point3::origin());
+        let look_dir = transform
+            .transform_vector(Vector3::new(0.0, 0.0, -1.0))
+            .normalize();
+
+        let yaw = Rad::atan2(look_dir.z, look_dir.x);
+        let pitch = Rad::asin(look_dir.y.clamp(-1.0, 1.0));
+
+        let (look_dir, up, right, forward) = calculate_local_directions(yaw, pitch);
+
+        Camera {
+            position,
+            yaw,
+            pitch,
+            look_dir,
+            right,
+            up,
+            forward,
+            projection,
             aspect,
-            near,
-            far,
         }
     }
 
@@ -63,7 +149,7 @@ impl Camera {
     }
 
     pub fn get_projection(&self) -> cgmath::Matrix4<f32> {
-        perspective(Deg(self.fovy), self.aspect, self.near, self.far)
+        self.projection.matrix(self.aspect)
     }
 
     pub(self) fn update_directions(&mut self) {
@@ -0,0 +1,1126 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::rc::Rc;
+
+use cgmath::{InnerSpace, Matrix4, Vector3};
+use image::{DynamicImage, ImageError};
+use serde_json::{json, Map, Value};
+
+use crate::animation::{Animation, Interpolation, Keyframes};
+use crate::camera::Projection;
+use crate::light::{Light, LightKind};
+use crate::material::{AlphaMode, CullMode, MaterialTextures, TextureTransform};
+use crate::mesh::{Topology, Vertex};
+use crate::model::{Model, Node, Skin};
+use crate::resources::{
+    AssetKey, ImageData, MaterialData, MeshData, ModelData, PrimitiveData, Vertices,
+};
+use crate::texture::{DefaultTextures, SamplerDesc, Texture, TextureImage};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    FailedToEncodeImage(ImageError),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::FailedToEncodeImage(e) => write!(f, "Failed to encode image: {}", e),
+            Error::Json(e) => write!(f, "Failed to serialize glTF: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        Error::Io(other)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(other: ImageError) -> Self {
+        Error::FailedToEncodeImage(other)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(other: serde_json::Error) -> Self {
+        Error::Json(other)
+    }
+}
+
+/// Writes the model in its current pose, as a single .glb file when the path has a `glb`
+/// extension and as a .gltf file with a .bin file next to it otherwise.
+pub fn write(model: &Model, defaults: &DefaultTextures, path: &Path) -> Result<(), Error> {
+    write_data(&read_back(model, defaults), path)
+}
+
+/// Writes model data, like a model built in code, in the format given by the extension of
+/// the path like `write`.
+pub fn write_data(data: &ModelData, path: &Path) -> Result<(), Error> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("glb") => fs::write(path, to_glb(data)?)?,
+        _ => {
+            let bin_path = path.with_extension("bin");
+            let uri = bin_path.file_name().unwrap_or_default().to_string_lossy();
+
+            let (json, buffer) = to_gltf(data, &uri)?;
+            if !buffer.is_empty() {
+                fs::write(&bin_path, &buffer)?;
+            }
+            fs::write(path, json)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds a .gltf JSON document referring to the returned buffer by `bin_uri`. The buffer
+/// holds the geometry, animations and images, and is empty when there are none.
+pub fn to_gltf(data: &ModelData, bin_uri: &str) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let (mut document, buffer) = Exporter::new(data).export()?;
+
+    if !buffer.is_empty() {
+        document["buffers"] = json!([{ "uri": bin_uri, "byteLength": buffer.len() }]);
+    }
+
+    Ok((serde_json::to_vec_pretty(&document)?, buffer))
+}
+
+/// Builds a binary glTF file, with the JSON document and the buffer in one file.
+pub fn to_glb(data: &ModelData) -> Result<Vec<u8>, Error> {
+    let (mut document, mut buffer) = Exporter::new(data).export()?;
+
+    // Both chunks must be 4 byte aligned, the JSON chunk is padded with spaces
+    pad(&mut buffer, 0);
+    if !buffer.is_empty() {
+        document["buffers"] = json!([{ "byteLength": buffer.len() }]);
+    }
+
+    let mut json = serde_json::to_vec(&document)?;
+    pad(&mut json, b' ');
+
+    let mut chunks = vec![(GLB_CHUNK_JSON, json)];
+    if !buffer.is_empty() {
+        chunks.push((GLB_CHUNK_BIN, buffer));
+    }
+
+    let length = 12 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+
+    for (kind, data) in chunks {
+        glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&kind.to_le_bytes());
+        glb.extend_from_slice(&data);
+    }
+
+    Ok(glb)
+}
+
+/// Reads the vertex data and textures of the model back from the GPU, as models do not keep
+/// a copy of them. Textures shared between materials become a single image, and the default
+/// textures standing for missing maps are left out.
+pub fn read_back(model: &Model, defaults: &DefaultTextures) -> ModelData {
+    let mut textures: Vec<*const Texture> = Vec::new();
+    let mut images = Vec::new();
+
+    let mut image_index = |texture: &Rc<Texture>| {
+        let defaults = [&defaults.white, &defaults.flat_normal];
+        if defaults.iter().any(|default| Rc::ptr_eq(default, texture)) {
+            return None;
+        }
+
+        if let Some(index) = textures.iter().position(|t| *t == Rc::as_ptr(texture)) {
+            return Some(index);
+        }
+
+        let image = texture.read_image();
+        images.push(ImageData {
+            key: AssetKey::content(image.as_raw()),
+            image: TextureImage::Ldr(image),
+            sampler: *texture.sampler(),
+            color_space: texture.color_space(),
+        });
+        textures.push(Rc::as_ptr(texture));
+
+        Some(images.len() - 1)
+    };
+
+    let materials = model
+        .materials()
+        .iter()
+        .map(|material| {
+            let textures = material.textures();
+
+            MaterialData {
+                textures: MaterialTextures {
+                    base_color: image_index(&textures.base_color),
+                    metallic_roughness: image_index(&textures.metallic_roughness),
+                    normal: image_index(&textures.normal),
+                    occlusion: image_index(&textures.occlusion),
+                    emissive: image_index(&textures.emissive),
+                    transforms: textures.transforms,
+                },
+                factors: *material.factors(),
+                alpha_mode: material.alpha_mode(),
+                cull_mode: material.cull_mode(),
+            }
+        })
+        .collect();
+
+    let meshes = model
+        .meshes()
+        .iter()
+        .enumerate()
+        .map(|(mesh_index, primitives)| {
+            primitives
+                .iter()
+                .enumerate()
+                .map(|(primitive_index, primitive)| {
+                    let mesh = primitive.mesh();
+                    let vertices = match primitive.is_skinned() {
+                        true => Vertices::Skinned(mesh.read_vertices()),
+                        false => Vertices::Static(mesh.read_vertices()),
+                    };
+                    let indices: Vec<u8> =
+                        mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+
+                    PrimitiveData {
+                        mesh: MeshData {
+                            key: AssetKey::content(&indices),
+                            mesh_index,
+                            primitive_index,
+                            vertices,
+                            indices: mesh.indices.clone(),
+                            topology: mesh.topology(),
+                            morph_targets: primitive
+                                .morph_targets()
+                                .map(|targets| targets.read_targets())
+                                .unwrap_or_default(),
+                        },
+                        material_index: primitive.material_index(),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    ModelData {
+        sources: Vec::new(),
+        nodes: model.nodes().to_vec(),
+        roots: model.roots().to_vec(),
+        meshes,
+        images,
+        materials,
+        skins: model.skins().to_vec(),
+        animations: model.animations().to_vec(),
+    }
+}
+
+fn pad(data: &mut Vec<u8>, value: u8) {
+    data.resize(data.len().next_multiple_of(4), value);
+}
+
+/// Builds the JSON document and the single buffer it refers to.
+struct Exporter<'a> {
+    data: &'a ModelData,
+
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+
+    images: Vec<Value>,
+    samplers: Vec<SamplerDesc>,
+    /// Textures along with the index of their image in the model data.
+    textures: Vec<(usize, Value)>,
+
+    cameras: Vec<Value>,
+    lights: Vec<Value>,
+    /// Whether any texture uses KHR_texture_transform.
+    texture_transforms: bool,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(data: &'a ModelData) -> Exporter<'a> {
+        Exporter {
+            data,
+            buffer: Vec::new(),
+            buffer_views: Vec::new(),
+            accessors: Vec::new(),
+            images: Vec::new(),
+            samplers: Vec::new(),
+            textures: Vec::new(),
+            cameras: Vec::new(),
+            lights: Vec::new(),
+            texture_transforms: false,
+        }
+    }
+
+    fn export(mut self) -> Result<(Value, Vec<u8>), Error> {
+        let data = self.data;
+
+        let materials = data
+            .materials
+            .iter()
+            .map(|material| self.material(material))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let meshes: Vec<Value> = data
+            .meshes
+            .iter()
+            .map(|primitives| self.mesh(primitives))
+            .collect();
+
+        let nodes: Vec<Value> = data.nodes.iter().map(|node| self.node(node)).collect();
+
+        let skins: Vec<Value> = data.skins.iter().map(|skin| self.skin(skin)).collect();
+
+        let animations: Vec<Value> = data
+            .animations
+            .iter()
+            .map(|animation| self.animation(animation))
+            .collect();
+
+        let mut document = Map::new();
+        document.insert(
+            "asset".to_string(),
+            json!({ "version": "2.0", "generator": env!("CARGO_PKG_NAME") }),
+        );
+        document.insert("scene".to_string(), json!(0));
+        document.insert("scenes".to_string(), json!([{ "nodes": data.roots }]));
+
+        let samplers: Vec<Value> = self.samplers.iter().map(sampler).collect();
+        let textures: Vec<Value> = self.textures.into_iter().map(|(_, t)| t).collect();
+
+        let arrays = [
+            ("nodes", nodes),
+            ("meshes", meshes),
+            ("materials", materials),
+            ("textures", textures),
+            ("images", self.images),
+            ("samplers", samplers),
+            ("skins", skins),
+            ("animations", animations),
+            ("cameras", self.cameras),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views),
+        ];
+        for (name, values) in arrays {
+            if !values.is_empty() {
+                document.insert(name.to_string(), Value::Array(values));
+            }
+        }
+
+        let mut extensions_used = Vec::new();
+        if self.texture_transforms {
+            extensions_used.push("KHR_texture_transform");
+        }
+        if !self.lights.is_empty() {
+            extensions_used.push("KHR_lights_punctual");
+            document.insert(
+                "extensions".to_string(),
+                json!({ "KHR_lights_punctual": { "lights": self.lights } }),
+            );
+        }
+        if !extensions_used.is_empty() {
+            document.insert("extensionsUsed".to_string(), json!(extensions_used));
+        }
+
+        Ok((Value::Object(document), self.buffer))
+    }
+
+    fn node(&mut self, node: &Node) -> Value {
+        let translation: [f32; 3] = node.translation.into();
+        let rotation = node.rotation;
+        let scale: [f32; 3] = node.scale.into();
+
+        let mut value = json!({
+            "translation": translation,
+            "rotation": [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s],
+            "scale": scale,
+        });
+
+        if !node.children.is_empty() {
+            value["children"] = json!(node.children);
+        }
+
+        if let Some(mesh) = node.mesh {
+            value["mesh"] = json!(mesh);
+
+            // Weights are only valid on nodes whose mesh has morph targets
+            if has_morph_targets(&self.data.meshes[mesh]) && !node.weights.is_empty() {
+                value["weights"] = json!(node.weights);
+            }
+        }
+
+        if let Some(skin) = node.skin {
+            value["skin"] = json!(skin);
+        }
+
+        if let Some(projection) = node.camera {
+            value["camera"] = json!(self.cameras.len());
+            self.cameras.push(camera(projection));
+        }
+
+        if let Some(node_light) = node.light {
+            value["extensions"] = json!({ "KHR_lights_punctual": { "light": self.lights.len() } });
+            self.lights.push(light(&node_light));
+        }
+
+        value
+    }
+
+    fn mesh(&mut self, primitives: &[PrimitiveData]) -> Value {
+        let primitives: Vec<Value> = primitives
+            .iter()
+            .map(|primitive| self.primitive(primitive))
+            .collect();
+
+        json!({ "primitives": primitives })
+    }
+
+    fn primitive(&mut self, primitive: &PrimitiveData) -> Value {
+        let mesh = &primitive.mesh;
+
+        let (vertices, skin_attributes) = match &mesh.vertices {
+            Vertices::Skinned(vertices) => {
+                let joints: Vec<[u32; 4]> = vertices.iter().map(|v| v.joints()).collect();
+                let weights: Vec<[f32; 4]> = vertices.iter().map(|v| v.weights().into()).collect();
+                let vertices = vertices.iter().map(|v| v.vertex()).collect();
+
+                (vertices, Some((joints, weights)))
+            }
+            Vertices::Static(vertices) => (vertices.clone(), None),
+        };
+
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| v.position().into()).collect();
+        let normals: Vec<[f32; 3]> = vertices.iter().map(|v| v.normal().into()).collect();
+        let uvs: Vec<[f32; 2]> = vertices.iter().map(|v| v.uv().into()).collect();
+        let tangents: Vec<[f32; 4]> = vertices
+            .iter()
+            .map(|v| {
+                let (normal, tangent) = (v.normal(), v.tangent());
+                let handedness = match normal.cross(tangent).dot(v.bitangent()) < 0.0 {
+                    true => -1.0,
+                    false => 1.0,
+                };
+                tangent.extend(handedness).into()
+            })
+            .collect();
+
+        let array_buffer = Some(gl::ARRAY_BUFFER);
+
+        let mut attributes = Map::new();
+        let mut attribute = |name: &str, accessor: usize| {
+            attributes.insert(name.to_string(), json!(accessor));
+        };
+
+        attribute("POSITION", self.push_floats(&positions, array_buffer, true));
+        attribute("NORMAL", self.push_floats(&normals, array_buffer, false));
+        attribute("TANGENT", self.push_floats(&tangents, array_buffer, false));
+        attribute("TEXCOORD_0", self.push_floats(&uvs, array_buffer, false));
+
+        // The second UV set defaults to the first one, so it is only written when it differs
+        let uvs1: Vec<[f32; 2]> = vertices.iter().map(|v| v.uv1().into()).collect();
+        if uvs1 != uvs {
+            attribute("TEXCOORD_1", self.push_floats(&uvs1, array_buffer, false));
+        }
+
+        // White vertex colors leave the base color unchanged, so they are left out
+        let colors: Vec<[f32; 4]> = vertices.iter().map(|v| v.color().into()).collect();
+        if colors.iter().any(|color| *color != [1.0; 4]) {
+            attribute("COLOR_0", self.push_floats(&colors, array_buffer, false));
+        }
+
+        if let Some((joints, weights)) = skin_attributes {
+            // glTF only allows unsigned byte and short joint indices
+            let joints: Vec<u8> = joints
+                .iter()
+                .flatten()
+                .flat_map(|joint| (*joint as u16).to_le_bytes())
+                .collect();
+            let accessor = self.push_accessor(
+                &joints,
+                gl::UNSIGNED_SHORT,
+                vertices.len(),
+                4,
+                array_buffer,
+                None,
+            );
+            attribute("JOINTS_0", accessor);
+            attribute("WEIGHTS_0", self.push_floats(&weights, array_buffer, false));
+        }
+
+        let indices: Vec<u8> = mesh
+            .indices
+            .iter()
+            .flat_map(|index| (*index as u32).to_le_bytes())
+            .collect();
+        let indices = self.push_accessor(
+            &indices,
+            gl::UNSIGNED_INT,
+            mesh.indices.len(),
+            1,
+            Some(gl::ELEMENT_ARRAY_BUFFER),
+            None,
+        );
+
+        let mode = match mesh.topology {
+            Topology::Points => gl::POINTS,
+            Topology::Triangles => gl::TRIANGLES,
+        };
+
+        let mut value = json!({
+            "attributes": attributes,
+            "indices": indices,
+            "material": primitive.material_index,
+            "mode": mode,
+        });
+
+        if !mesh.morph_targets.is_empty() {
+            let targets: Vec<Value> = mesh
+                .morph_targets
+                .iter()
+                .map(|target| {
+                    let positions =
+                        self.push_floats(&vectors(&target.positions), array_buffer, true);
+                    let normals = self.push_floats(&vectors(&target.normals), array_buffer, false);
+                    let tangents =
+                        self.push_floats(&vectors(&target.tangents), array_buffer, false);
+
+                    json!({ "POSITION": positions, "NORMAL": normals, "TANGENT": tangents })
+                })
+                .collect();
+
+            value["targets"] = json!(targets);
+        }
+
+        value
+    }
+
+    fn material(&mut self, material: &MaterialData) -> Result<Value, Error> {
+        let (textures, factors) = (&material.textures, &material.factors);
+        let base_color: [f32; 4] = factors.base_color.into();
+        let emissive: [f32; 3] = factors.emissive.into();
+
+        let mut pbr = json!({
+            "baseColorFactor": base_color,
+            "metallicFactor": factors.metallic,
+            "roughnessFactor": factors.roughness,
+        });
+        let mut value = json!({
+            "emissiveFactor": emissive,
+            "doubleSided": material.cull_mode == CullMode::Disabled,
+        });
+
+        let transforms = &textures.transforms;
+
+        if let Some(info) = self.texture_info(textures.base_color, &transforms.base_color)? {
+            pbr["baseColorTexture"] = info;
+        }
+        if let Some(info) =
+            self.texture_info(textures.metallic_roughness, &transforms.metallic_roughness)?
+        {
+            pbr["metallicRoughnessTexture"] = info;
+        }
+        if let Some(mut info) = self.texture_info(textures.normal, &transforms.normal)? {
+            info["scale"] = json!(factors.normal_scale);
+            value["normalTexture"] = info;
+        }
+        if let Some(mut info) = self.texture_info(textures.occlusion, &transforms.occlusion)? {
+            info["strength"] = json!(factors.occlusion_strength);
+            value["occlusionTexture"] = info;
+        }
+        if let Some(info) = self.texture_info(textures.emissive, &transforms.emissive)? {
+            value["emissiveTexture"] = info;
+        }
+
+        value["pbrMetallicRoughness"] = pbr;
+
+        match material.alpha_mode {
+            AlphaMode::Opaque => value["alphaMode"] = json!("OPAQUE"),
+            AlphaMode::Mask { cutoff } => {
+                value["alphaMode"] = json!("MASK");
+                value["alphaCutoff"] = json!(cutoff);
+            }
+            AlphaMode::Blend => value["alphaMode"] = json!("BLEND"),
+        }
+
+        Ok(value)
+    }
+
+    /// References a texture from a material, with its UV set and KHR_texture_transform.
+    fn texture_info(
+        &mut self,
+        image: Option<usize>,
+        transform: &TextureTransform,
+    ) -> Result<Option<Value>, Error> {
+        let Some(image) = image else {
+            return Ok(None);
+        };
+        let index = self.push_texture(image)?;
+
+        let mut info = json!({ "index": index });
+        if transform.uv_set != 0 {
+            info["texCoord"] = json!(transform.uv_set);
+        }
+
+        if !transform.is_identity() {
+            let offset: [f32; 2] = transform.offset.into();
+            let scale: [f32; 2] = transform.scale.into();
+
+            info["extensions"] = json!({
+                "KHR_texture_transform": {
+                    "offset": offset,
+                    "rotation": transform.rotation,
+                    "scale": scale,
+                }
+            });
+            self.texture_transforms = true;
+        }
+
+        Ok(Some(info))
+    }
+
+    /// Adds a texture along with its image and sampler, once for every image shared between
+    /// materials. Images are stored as PNG, so HDR images are clamped to 8 bits.
+    fn push_texture(&mut self, image: usize) -> Result<usize, Error> {
+        if let Some(index) = self.textures.iter().position(|(i, _)| *i == image) {
+            return Ok(index);
+        }
+
+        let data = &self.data.images[image];
+        let pixels = match &data.image {
+            TextureImage::Ldr(pixels) => DynamicImage::ImageRgba8(pixels.clone()),
+            TextureImage::Hdr(pixels, _) => {
+                DynamicImage::ImageRgba8(DynamicImage::ImageRgba32F(pixels.clone()).to_rgba8())
+            }
+        };
+
+        let mut png = Cursor::new(Vec::new());
+        pixels.write_to(&mut png, image::ImageFormat::Png)?;
+
+        let buffer_view = self.push_view(png.get_ref(), None);
+        let source = self.images.len();
+        self.images
+            .push(json!({ "bufferView": buffer_view, "mimeType": "image/png" }));
+
+        let sampler = match self.samplers.iter().position(|s| *s == data.sampler) {
+            Some(index) => index,
+            None => {
+                self.samplers.push(data.sampler);
+                self.samplers.len() - 1
+            }
+        };
+
+        self.textures
+            .push((image, json!({ "source": source, "sampler": sampler })));
+
+        Ok(self.textures.len() - 1)
+    }
+
+    fn skin(&mut self, skin: &Skin) -> Value {
+        let matrices: Vec<[f32; 16]> = skin
+            .inverse_bind_matrices
+            .iter()
+            .map(|matrix| *<Matrix4<f32> as AsRef<[f32; 16]>>::as_ref(matrix))
+            .collect();
+
+        json!({
+            "joints": skin.joints,
+            "inverseBindMatrices": self.push_floats(&matrices, None, false),
+        })
+    }
+
+    fn animation(&mut self, animation: &Animation) -> Value {
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+
+        for channel in animation.channels() {
+            // Weights can only be animated on nodes whose mesh has morph targets
+            let (path, output) = match channel.keyframes() {
+                Keyframes::Translation(values) => (
+                    "translation",
+                    self.push_floats(&vectors(values), None, false),
+                ),
+                Keyframes::Scale(values) => {
+                    ("scale", self.push_floats(&vectors(values), None, false))
+                }
+                Keyframes::Rotation(values) => {
+                    let rotations: Vec<[f32; 4]> = values
+                        .iter()
+                        .map(|value| [value.v.x, value.v.y, value.v.z, value.s])
+                        .collect();
+                    ("rotation", self.push_floats(&rotations, None, false))
+                }
+                Keyframes::MorphWeights(_) if !self.has_morph_targets(channel.node()) => continue,
+                Keyframes::MorphWeights(values) => {
+                    let weights: Vec<[f32; 1]> =
+                        values.iter().flatten().map(|weight| [*weight]).collect();
+                    ("weights", self.push_floats(&weights, None, false))
+                }
+            };
+
+            let times: Vec<[f32; 1]> = channel.times().iter().map(|time| [*time]).collect();
+            let input = self.push_floats(&times, None, true);
+
+            let interpolation = match channel.interpolation() {
+                Interpolation::Linear => "LINEAR",
+                Interpolation::Step => "STEP",
+                Interpolation::CubicSpline => "CUBICSPLINE",
+            };
+
+            channels.push(json!({
+                "sampler": samplers.len(),
+                "target": { "node": channel.node(), "path": path },
+            }));
+            samplers.push(json!({
+                "input": input,
+                "output": output,
+                "interpolation": interpolation,
+            }));
+        }
+
+        json!({ "samplers": samplers, "channels": channels })
+    }
+
+    fn has_morph_targets(&self, node: usize) -> bool {
+        let mesh = self.data.nodes.get(node).and_then(|node| node.mesh);
+
+        mesh.is_some_and(|mesh| has_morph_targets(&self.data.meshes[mesh]))
+    }
+
+    /// Adds the values as a float accessor with one to four components, or a matrix with 16.
+    /// Position and animation input accessors must also store their bounds.
+    fn push_floats<const N: usize>(
+        &mut self,
+        values: &[[f32; N]],
+        target: Option<gl::types::GLenum>,
+        bounds: bool,
+    ) -> usize {
+        let data: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+
+        let bounds = match (bounds, values.first()) {
+            (true, Some(first)) => {
+                Some(values.iter().fold((*first, *first), |(min, max), value| {
+                    (
+                        std::array::from_fn(|i| min[i].min(value[i])),
+                        std::array::from_fn(|i| max[i].max(value[i])),
+                    )
+                }))
+            }
+            _ => None,
+        };
+
+        self.push_accessor(
+            &data,
+            gl::FLOAT,
+            values.len(),
+            N,
+            target,
+            bounds.map(|(min, max)| (json!(min.to_vec()), json!(max.to_vec()))),
+        )
+    }
+
+    fn push_accessor(
+        &mut self,
+        data: &[u8],
+        component_type: gl::types::GLenum,
+        count: usize,
+        components: usize,
+        target: Option<gl::types::GLenum>,
+        bounds: Option<(Value, Value)>,
+    ) -> usize {
+        let accessor_type = match components {
+            1 => "SCALAR",
+            2 => "VEC2",
+            3 => "VEC3",
+            4 => "VEC4",
+            16 => "MAT4",
+            _ => unreachable!("unsupported accessor with {} components", components),
+        };
+
+        let buffer_view = self.push_view(data, target);
+
+        let mut accessor = json!({
+            "bufferView": buffer_view,
+            "componentType": component_type,
+            "count": count,
+            "type": accessor_type,
+        });
+        if let Some((min, max)) = bounds {
+            accessor["min"] = min;
+            accessor["max"] = max;
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_view(&mut self, data: &[u8], target: Option<gl::types::GLenum>) -> usize {
+        // Every view starts 4 byte aligned, enough for all component types
+        pad(&mut self.buffer, 0);
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+
+        let mut buffer_view = json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": data.len(),
+        });
+        if let Some(target) = target {
+            buffer_view["target"] = json!(target);
+        }
+
+        self.buffer_views.push(buffer_view);
+        self.buffer_views.len() - 1
+    }
+}
+
+fn vectors(values: &[Vector3<f32>]) -> Vec<[f32; 3]> {
+    values.iter().map(|value| (*value).into()).collect()
+}
+
+fn has_morph_targets(primitives: &[PrimitiveData]) -> bool {
+    primitives
+        .iter()
+        .any(|primitive| !primitive.mesh.morph_targets.is_empty())
+}
+
+fn sampler(sampler: &SamplerDesc) -> Value {
+    json!({
+        "magFilter": sampler.mag_filter_to_gl(),
+        "minFilter": sampler.min_filter_to_gl(),
+        "wrapS": sampler.wrap_s.to_gl(),
+        "wrapT": sampler.wrap_t.to_gl(),
+    })
+}
+
+fn camera(projection: Projection) -> Value {
+    match projection {
+        Projection::Perspective {
+            yfov,
+            aspect,
+            near,
+            far,
+        } => {
+            let mut perspective = json!({ "yfov": yfov.0, "znear": near });
+            if let Some(aspect) = aspect {
+                perspective["aspectRatio"] = json!(aspect);
+            }
+            if let Some(far) = far {
+                perspective["zfar"] = json!(far);
+            }
+
+            json!({ "type": "perspective", "perspective": perspective })
+        }
+        Projection::Orthographic {
+            xmag,
+            ymag,
+            near,
+            far,
+        } => json!({
+            "type": "orthographic",
+            "orthographic": { "xmag": xmag, "ymag": ymag, "znear": near, "zfar": far },
+        }),
+    }
+}
+
+fn light(light: &Light) -> Value {
+    let color: [f32; 3] = light.color.into();
+
+    let mut value = json!({
+        "color": color,
+        "intensity": light.intensity,
+    });
+
+    match light.kind {
+        LightKind::Directional => value["type"] = json!("directional"),
+        LightKind::Point => value["type"] = json!("point"),
+        LightKind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => {
+            value["type"] = json!("spot");
+            value["spot"] = json!({
+                "innerConeAngle": inner_cone_angle.0,
+                "outerConeAngle": outer_cone_angle.0,
+            });
+        }
+    }
+
+    if let Some(range) = light.range {
+        value["range"] = json!(range);
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Quaternion, Rotation3, Vector4};
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::material::{MaterialFactors, TextureTransforms};
+    use crate::mesh::{MeshVertex, SkinnedMeshVertex};
+    use crate::resources::{AssetReader, MemoryMount};
+    use crate::texture::{ColorSpace, Filter, Wrap};
+
+    /// Scene with a skinned triangle below a transformed root, bent by a joint node.
+    fn scene() -> ModelData {
+        let nodes = vec![
+            Node::new(
+                None,
+                None,
+                vec![1, 2],
+                Vector3::new(1.0, 2.0, 3.0),
+                Quaternion::from_angle_y(Deg(90.0)),
+                Vector3::new(2.0, 2.0, 2.0),
+                Vec::new(),
+            ),
+            Node::new(
+                Some(0),
+                Some(0),
+                Vec::new(),
+                Vector3::new(0.0, 0.0, 0.0),
+                Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 1.0),
+                Vec::new(),
+            ),
+            Node::new(
+                None,
+                None,
+                Vec::new(),
+                Vector3::new(0.0, 1.5, 0.0),
+                Quaternion::from_angle_z(Deg(30.0)),
+                Vector3::new(1.0, 0.5, 1.0),
+                Vec::new(),
+            ),
+        ];
+
+        let normal = Vector3::new(0.0, 0.0, 1.0);
+        let tangent = Vector3::new(1.0, 0.0, 0.0);
+        let vertex = |position: [f32; 3], uv: [f32; 2], joints, weights: [f32; 4]| {
+            let vertex = MeshVertex::new(position, uv, normal, tangent, normal.cross(tangent));
+            SkinnedMeshVertex::new(vertex, joints, weights)
+        };
+        let vertices = vec![
+            vertex(
+                [0.0, 0.0, 0.0],
+                [0.0, 1.0],
+                [0, 0, 0, 0],
+                [1.0, 0.0, 0.0, 0.0],
+            ),
+            vertex(
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0],
+                [0, 1, 0, 0],
+                [0.5, 0.5, 0.0, 0.0],
+            ),
+            vertex(
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0],
+                [1, 0, 0, 0],
+                [0.75, 0.25, 0.0, 0.0],
+            ),
+        ];
+
+        let primitive = PrimitiveData {
+            mesh: MeshData {
+                key: AssetKey::content(b"triangle"),
+                mesh_index: 0,
+                primitive_index: 0,
+                vertices: Vertices::Skinned(vertices),
+                indices: vec![0, 1, 2],
+                topology: Topology::Triangles,
+                morph_targets: Vec::new(),
+            },
+            material_index: 0,
+        };
+
+        let base_color = RgbaImage::from_fn(2, 2, |x, y| {
+            image::Rgba([x as u8 * 255, y as u8 * 255, 64, 255])
+        });
+        let normal_map = RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255]));
+        let images = vec![
+            ImageData {
+                key: AssetKey::content(base_color.as_raw()),
+                image: TextureImage::Ldr(base_color),
+                sampler: SamplerDesc::default(),
+                color_space: ColorSpace::Srgb,
+            },
+            ImageData {
+                key: AssetKey::content(normal_map.as_raw()),
+                image: TextureImage::Ldr(normal_map),
+                sampler: SamplerDesc {
+                    wrap_s: Wrap::ClampToEdge,
+                    wrap_t: Wrap::MirroredRepeat,
+                    min_filter: Filter::Nearest,
+                    mag_filter: Filter::Nearest,
+                    mipmap_filter: None,
+                    ..SamplerDesc::default()
+                },
+                color_space: ColorSpace::Linear,
+            },
+        ];
+
+        let material = MaterialData {
+            textures: MaterialTextures {
+                base_color: Some(0),
+                metallic_roughness: None,
+                normal: Some(1),
+                occlusion: None,
+                emissive: None,
+                transforms: TextureTransforms::default(),
+            },
+            factors: MaterialFactors {
+                base_color: Vector4::new(0.5, 0.25, 1.0, 0.75),
+                metallic: 0.25,
+                roughness: 0.5,
+                normal_scale: 0.5,
+                occlusion_strength: 1.0,
+                emissive: Vector3::new(0.0, 0.5, 1.0),
+            },
+            alpha_mode: AlphaMode::Mask { cutoff: 0.25 },
+            cull_mode: CullMode::Disabled,
+        };
+
+        let skin = Skin::new(
+            vec![0, 2],
+            vec![
+                Matrix4::from_scale(1.0),
+                Matrix4::from_translation(Vector3::new(0.0, -1.5, 0.0)),
+            ],
+        );
+
+        ModelData {
+            sources: Vec::new(),
+            nodes,
+            roots: vec![0],
+            meshes: vec![vec![primitive]],
+            images,
+            materials: vec![material],
+            skins: vec![skin],
+            animations: Vec::new(),
+        }
+    }
+
+    fn assert_round_trip(exported: &ModelData, imported: &ModelData) {
+        assert_eq!(imported.nodes, exported.nodes);
+        assert_eq!(imported.roots, exported.roots);
+        assert_eq!(imported.skins, exported.skins);
+
+        let (material, expected) = (&imported.materials[0], &exported.materials[0]);
+        assert_eq!(material.factors, expected.factors);
+        assert_eq!(material.alpha_mode, expected.alpha_mode);
+        assert_eq!(material.cull_mode, expected.cull_mode);
+        assert_eq!(material.textures.transforms, expected.textures.transforms);
+        assert_eq!(material.textures.metallic_roughness, None);
+        assert_eq!(material.textures.occlusion, None);
+        assert_eq!(material.textures.emissive, None);
+
+        let textures = [
+            (material.textures.base_color, expected.textures.base_color),
+            (material.textures.normal, expected.textures.normal),
+        ];
+        for (image, expected) in textures {
+            let (image, expected) = (
+                &imported.images[image.unwrap()],
+                &exported.images[expected.unwrap()],
+            );
+            let (TextureImage::Ldr(pixels), TextureImage::Ldr(expected_pixels)) =
+                (&image.image, &expected.image)
+            else {
+                panic!("expected 8-bit images");
+            };
+            assert_eq!(pixels, expected_pixels);
+            assert_eq!(image.sampler, expected.sampler);
+            assert_eq!(image.color_space, expected.color_space);
+        }
+
+        let (mesh, expected) = (&imported.meshes[0][0].mesh, &exported.meshes[0][0].mesh);
+        assert_eq!(imported.meshes[0][0].material_index, 0);
+        assert_eq!(mesh.indices, expected.indices);
+        assert_eq!(mesh.topology, expected.topology);
+
+        let (Vertices::Skinned(vertices), Vertices::Skinned(expected)) =
+            (&mesh.vertices, &expected.vertices)
+        else {
+            panic!("expected skinned vertices");
+        };
+        assert_eq!(vertices.len(), expected.len());
+        for (vertex, expected) in vertices.iter().zip(expected) {
+            assert_eq!(vertex.joints(), expected.joints());
+            assert_eq!(vertex.weights(), expected.weights());
+
+            let (vertex, expected) = (vertex.vertex(), expected.vertex());
+            assert_eq!(vertex.position(), expected.position());
+            assert_eq!(vertex.uv(), expected.uv());
+            assert_eq!(vertex.uv1(), expected.uv1());
+            assert_eq!(vertex.normal(), expected.normal());
+            assert_eq!(vertex.tangent(), expected.tangent());
+            assert_eq!(vertex.bitangent(), expected.bitangent());
+            assert_eq!(vertex.color(), expected.color());
+        }
+    }
+
+    #[test]
+    fn round_trips_gltf_with_bin() {
+        let scene = scene();
+        let (json, bin) = to_gltf(&scene, "scene.bin").unwrap();
+
+        let reader = AssetReader::new().with_mount(
+            MemoryMount::new()
+                .with_file("scene.gltf", json)
+                .with_file("scene.bin", bin),
+        );
+
+        assert_round_trip(&scene, &reader.read_gltf("scene.gltf").unwrap());
+    }
+
+    #[test]
+    fn round_trips_glb() {
+        let scene = scene();
+        let glb = to_glb(&scene).unwrap();
+        assert_eq!(glb.len() % 4, 0);
+
+        let reader = AssetReader::new().with_mount(MemoryMount::new().with_file("scene.glb", glb));
+
+        assert_round_trip(&scene, &reader.read_gltf("scene.glb").unwrap());
+    }
+
+    #[test]
+    fn writes_shared_images_once() {
+        let mut scene = scene();
+        scene.materials[0].textures.emissive = Some(0);
+
+        let (json, _) = to_gltf(&scene, "scene.bin").unwrap();
+        let document: Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(document["images"].as_array().unwrap().len(), 2);
+        assert_eq!(document["textures"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            document["materials"][0]["emissiveTexture"]["index"],
+            document["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"]["index"]
+        );
+    }
+}
@@ -1,4 +1,7 @@
+mod animation;
 mod camera;
+mod gltf_export;
+mod light;
 mod material;
 mod mesh;
 mod model;
@@ -10,21 +13,34 @@ mod uniform;
 mod uniform_buffer_object;
 
 use std::path::Path;
+use std::time::Duration;
 
-use cgmath::{Array, Deg, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix};
+use cgmath::{Deg, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Vector3};
 use glfw::{Context, OpenGlProfileHint, WindowHint};
 
+use animation::AnimationPlayer;
 use camera::{Camera, CameraController};
+use light::{Light, LightBuffer, LightKind};
 
-use resources::ResourceLoader;
-use shader::{Program, Shader};
-use skybox::Skybox;
+use resources::{AssetKey, AssetWatcher, BackgroundLoader, LoadStatus, ResourceLoader};
+use shader::Program;
+use skybox::{Skybox, SkyboxSource};
+use texture::DefaultTextures;
 use uniform::{Uniform, Uniform3f};
 use uniform_buffer_object::UniformBufferObject;
 
 const WIDTH: u32 = 1080;
 const HEIGHT: u32 = 720;
 
+/// Bytes of texture and mesh data uploaded per frame while models are loading.
+const UPLOAD_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Width of the skybox faces converted from a panorama.
+const SKYBOX_FACE_SIZE: u32 = 1024;
+
+const VERTEX_SHADER: &str = "assets/shaders/shader.vert";
+const FRAGMENT_SHADER: &str = "assets/shaders/shader.frag";
+
 pub fn run() {
     // INITIALIZE GRAPHICS AND WINDOW CONTEXT
 
@@ -33,6 +49,7 @@ pub fn run() {
     glfw.window_hint(WindowHint::ContextVersion(4, 6));
     glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
     glfw.window_hint(WindowHint::OpenGlForwardCompat(true));
+    glfw.window_hint(WindowHint::SRgbCapable(true));
 
     let (mut window, events) = glfw
         .create_window(
@@ -50,59 +67,98 @@ pub fn run() {
 
     // INIT RESOURCES LOADER
 
-    let resources = ResourceLoader::from_relative_exe_path(Path::new("")).unwrap();
+    let resources = ResourceLoader::from_default_roots().unwrap();
 
     let gl = gl::Gl::load_with(|s| window.get_proc_address(s) as *const _);
 
+    let aspect = WIDTH as f32 / HEIGHT as f32;
     let camera_controller = CameraController::new(5.0, 0.15);
     let mut camera = Camera::new(
         (0.0, 0.0, 3.0),
         Deg(-90.0),
         Deg(0.0),
         45.0,
-        WIDTH as f32 / HEIGHT as f32,
+        aspect,
         0.01,
         100.0,
     );
 
     // CREATE SHADER PROGRAM
-    let vertex_shader =
-        Shader::from_vertex_source(&gl, &resources, "assets/shaders/shader.vert").unwrap();
-    let fragment_shader =
-        Shader::from_fragment_source(&gl, &resources, "assets/shaders/shader.frag").unwrap();
-    let program = Program::from_shaders(&gl, &[vertex_shader, fragment_shader]).unwrap();
+    let program = Program::from_files(&gl, &resources, VERTEX_SHADER, FRAGMENT_SHADER).unwrap();
 
     // --- TEMP ---
-    let model_3d = resources.load_model(&gl, "assets/models/stone_cube/scene.gltf", &program);
+    let default_textures = DefaultTextures::new(&gl);
+
+    // The model to show can be given as the first argument, relative to the asset mounts
+    let model_path = std::env::args()
+        .nth(1)
+        .unwrap_or("assets/models/stone_cube/scene.gltf".to_string());
+
+    // The model is decoded on worker threads and uploaded over the first frames
+    let mut loader = BackgroundLoader::new(&gl, &resources, &default_textures);
+    let model = loader.load_model(&model_path, &program).unwrap();
     // ------------
 
+    // Pressing E writes the scene in its current pose to the second argument, as .glb or .gltf
+    let export_path = std::env::args().nth(2).unwrap_or("export.glb".to_string());
+
+    let mut active_camera = 0;
+    let mut model_loaded = false;
+
+    // ANIMATION
+
+    let mut animation_player = AnimationPlayer::new();
+
     // GLOBAL UNIFORMS
     let matrix4_size = std::mem::size_of::<Matrix4<f32>>() as isize;
 
     let matrix_ubo = UniformBufferObject::new(
         &gl,
         0,
-        &[
-            ("projection", matrix4_size),
-            ("view", matrix4_size),
-            ("model", matrix4_size),
-            ("normalMatrix", matrix4_size),
-        ],
+        &[("projection", matrix4_size), ("view", matrix4_size)],
     );
 
-    let light_ubo = UniformBufferObject::new(
-        &gl,
-        1,
-        &[("lightPos", std::mem::size_of::<Point3<f32>>() as isize)],
-    );
+    let light_buffer = LightBuffer::new(&gl);
+
+    // Scenes without lights of their own get a white point light
+    let default_light = Light {
+        position: Point3::new(-1.5, 1.5, 1.5),
+        ..Light::new(LightKind::Point, Vector3::new(1.0, 1.0, 1.0), 20.0, None)
+    };
 
     // SKYBOX
-    let skybox = Skybox::new(&gl, &resources);
+    // An equirectangular panorama, like an .hdr or .exr HDRI, can be given as the third
+    // argument in place of the default faces
+    let skybox_source = match std::env::args().nth(3) {
+        Some(path) => SkyboxSource::Panorama {
+            path,
+            face_size: SKYBOX_FACE_SIZE,
+        },
+        None => SkyboxSource::Faces(
+            ["right", "left", "top", "bottom", "front", "back"]
+                .map(|face| format!("assets/skybox/sky/{}.jpg", face)),
+        ),
+    };
+    let skybox = Skybox::new(&gl, &resources, skybox_source).unwrap();
+
+    // HOT RELOAD
+
+    // The shaders, the skybox and the model are rebuilt when their files change
+    let mut watcher = AssetWatcher::new(resources.reader(), Duration::from_millis(500));
+    watcher.watch(VERTEX_SHADER);
+    watcher.watch(FRAGMENT_SHADER);
+    for file in skybox.files() {
+        watcher.watch(file);
+    }
 
     // ENABLE DEPTH TESTING
 
     unsafe { gl.Enable(gl::DEPTH_TEST) };
 
+    // CONVERT THE LINEAR SHADER OUTPUT TO SRGB
+
+    unsafe { gl.Enable(gl::FRAMEBUFFER_SRGB) };
+
     // EVENT LOOP
 
     // x respresents forward movement, y side movement and z vertical movement
@@ -116,31 +172,106 @@ pub fn run() {
         let delta_time = current_time - initial_time;
         initial_time = current_time;
 
+        // UPLOAD LOADED ASSETS
+
+        let was_loading = model.is_loading();
+        loader.upload(UPLOAD_BUDGET);
+
+        if was_loading {
+            match &*model.status() {
+                LoadStatus::Loading => {}
+                LoadStatus::Ready => {
+                    let model_3d = model.get();
+
+                    // Start from the first camera of the scene, if any
+                    if !model_loaded {
+                        if let Some(scene_camera) = model_3d
+                            .cameras(Matrix4::identity(), aspect)
+                            .into_iter()
+                            .next()
+                        {
+                            camera = scene_camera;
+                        }
+                        model_loaded = true;
+                    }
+
+                    animation_player = AnimationPlayer::new();
+                    if !model_3d.animations().is_empty() {
+                        animation_player.play(0);
+                    }
+
+                    for file in model.files().iter() {
+                        watcher.watch(file);
+                    }
+
+                    // Textures and meshes only the replaced model used are freed
+                    let evicted = resources.cache().evict_unused();
+                    println!("Loaded {}, evicted {} unused assets", model_path, evicted);
+                }
+                LoadStatus::Failed(e) => println!("Failed to load {}: {}", model_path, e),
+            }
+        }
+
+        // RELOAD CHANGED ASSETS
+
+        for path in watcher.poll() {
+            let is_shader = path == Path::new(VERTEX_SHADER) || path == Path::new(FRAGMENT_SHADER);
+
+            let result = match is_shader {
+                true => program.reload_files(&resources, VERTEX_SHADER, FRAGMENT_SHADER),
+                false => match skybox.reload(&resources, &path) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => match resources.reload_textures(&path) {
+                        Ok(0) => {
+                            // Any other file of the model, its meshes are cached by the key
+                            // of the model file and have to be read again
+                            resources
+                                .cache()
+                                .invalidate(&AssetKey::path(Path::new(&model_path)));
+                            loader
+                                .reload_model(&model, &model_path, &program)
+                                .map_err(|e| e.to_string())
+                        }
+                        Ok(_) => Ok(()),
+                        Err(e) => Err(e.to_string()),
+                    },
+                    Err(e) => Err(e),
+                },
+            };
+
+            // Failed assets keep their previous version
+            match result {
+                Ok(()) => println!("Reloaded {}", path.display()),
+                Err(e) => println!("Failed to reload {}: {}", path.display(), e),
+            }
+        }
+
+        let mut model_3d = model.get_mut();
+
+        animation_player.update(delta_time, &mut model_3d);
+
         unsafe {
             gl.ClearColor(0.3, 0.4, 0.6, 1.0);
             gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
             // SET GLOBAL UNIFORMS
-            let model_matrix = Matrix4::<f32>::from_angle_x(cgmath::Deg(-90.0));
-            let normal_matrix = model_matrix.invert().unwrap().transpose();
-
             matrix_ubo.write_sub_data("projection", camera.get_projection().as_ptr().cast());
             matrix_ubo.write_sub_data("view", camera.get_view().as_ptr().cast());
-            matrix_ubo.write_sub_data("model", model_matrix.as_ptr().cast());
-            matrix_ubo.write_sub_data("normalMatrix", normal_matrix.as_ptr().cast());
 
-            let light_pos = Point3::<f32>::new(-1.5, 1.5, 1.5);
-            light_ubo.write_sub_data("lightPos", light_pos.as_ptr().cast());
+            // Lights follow their nodes when animated
+            let lights = match model_3d.lights(Matrix4::identity()) {
+                lights if lights.is_empty() => vec![default_light],
+                lights => lights,
+            };
+            light_buffer.write(&lights);
 
             // SKYBOX
             skybox.draw(&camera);
 
-            let uniforms: Vec<Box<dyn Uniform>> = vec![
-                // Uniform3f::new("lightPos", light_pos),
-                Uniform3f::new("viewPos", camera.get_position()),
-            ];
+            let uniforms: Vec<Box<dyn Uniform>> =
+                vec![Uniform3f::new("viewPos", camera.get_position())];
 
-            model_3d.draw(uniforms);
+            model_3d.draw(Matrix4::identity(), camera.get_position(), uniforms);
         }
 
         glfw.poll_events();
@@ -157,6 +288,49 @@ pub fn run() {
                         glfw::Key::D => movement_direction.z += 1.0,
                         glfw::Key::Space => movement_direction.y += 1.0,
                         glfw::Key::LeftShift => movement_direction.y -= 1.0,
+                        glfw::Key::C => {
+                            // Switch to the next camera of the scene
+                            let cameras = model_3d.cameras(Matrix4::identity(), aspect);
+                            if !cameras.is_empty() {
+                                active_camera = (active_camera + 1) % cameras.len();
+                                camera = cameras.into_iter().nth(active_camera).unwrap();
+                            }
+                        }
+                        glfw::Key::P => match animation_player.is_playing() {
+                            true => animation_player.pause(),
+                            false => animation_player.resume(),
+                        },
+                        glfw::Key::N => {
+                            // Cross-fade into the next animation of the model
+                            let count = model_3d.animations().len();
+                            if count > 0 {
+                                let next = animation_player
+                                    .current_animation()
+                                    .map_or(0, |current| (current + 1) % count);
+                                animation_player.cross_fade(next, 0.5);
+                            }
+                        }
+                        glfw::Key::E => {
+                            let path = Path::new(&export_path);
+                            match gltf_export::write(&model_3d, &default_textures, path) {
+                                Ok(()) => println!("Exported the scene to {}", path.display()),
+                                Err(e) => println!("Failed to export the scene: {}", e),
+                            }
+                        }
+                        glfw::Key::R => {
+                            // Reload the model in the background, its meshes and textures
+                            // come from the cache. On failure the current model stays.
+                            if let Err(e) = loader.reload_model(&model, &model_path, &program) {
+                                println!("Failed to reload {}: {}", model_path, e);
+                            }
+                        }
+                        glfw::Key::L => {
+                            animation_player.set_looping(!animation_player.is_looping())
+                        }
+                        glfw::Key::Up => animation_player.set_speed(animation_player.speed() * 2.0),
+                        glfw::Key::Down => {
+                            animation_player.set_speed(animation_player.speed() * 0.5)
+                        }
                         _ => {}
                     },
                     (key, glfw::Action::Release) => match key {
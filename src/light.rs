@@ -0,0 +1,134 @@
+use cgmath::{Angle, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Transform, Vector3};
+
+use crate::uniform_buffer_object::UniformBufferObject;
+
+/// Maximum number of lights, must match the size of the lights array in the fragment shader.
+pub const MAX_LIGHTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Infinitely far away light shining along its direction, intensity is in lux.
+    Directional,
+    /// Light shining in all directions from its position, intensity is in candela.
+    Point,
+    /// Light shining in a cone along its direction, intensity is in candela. The intensity
+    /// falls off from the inner to the outer cone angle.
+    Spot {
+        inner_cone_angle: Rad<f32>,
+        outer_cone_angle: Rad<f32>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Point3<f32>,
+    pub direction: Vector3<f32>,
+    /// Linear color of the light.
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// Distance at which the light reaches zero, `None` is infinite.
+    pub range: Option<f32>,
+}
+
+impl Light {
+    /// Creates a light at the origin shining down the -Z axis.
+    pub fn new(kind: LightKind, color: Vector3<f32>, intensity: f32, range: Option<f32>) -> Light {
+        Light {
+            kind,
+            position: Point3::origin(),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn transformed(&self, transform: Matrix4<f32>) -> Light {
+        Light {
+            position: transform.transform_point(self.position),
+            direction: transform.transform_vector(self.direction).normalize(),
+            ..*self
+        }
+    }
+}
+
+/// Light laid out like the `Light` struct of the fragment shader.
+#[derive(Default)]
+#[repr(C)]
+struct LightData {
+    /// Position and kind: 0 for directional, 1 for point and 2 for spot lights.
+    position: [f32; 4],
+    /// Direction and range, 0 when infinite.
+    direction: [f32; 4],
+    /// Color multiplied by the intensity.
+    color: [f32; 4],
+    /// Scale and offset turning the cosine of the angle to the spot direction into the
+    /// cone attenuation.
+    cone: [f32; 4],
+}
+
+impl LightData {
+    fn new(light: &Light) -> LightData {
+        let (kind, cone) = match light.kind {
+            LightKind::Directional => (0.0, [0.0; 4]),
+            LightKind::Point => (1.0, [0.0; 4]),
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                let (cos_inner, cos_outer) = (inner_cone_angle.cos(), outer_cone_angle.cos());
+                let scale = 1.0 / (cos_inner - cos_outer).max(0.001);
+                (2.0, [scale, -cos_outer * scale, 0.0, 0.0])
+            }
+        };
+
+        let Point3 { x, y, z } = light.position;
+        let direction = light.direction;
+        let color = light.color * light.intensity;
+
+        LightData {
+            position: [x, y, z, kind],
+            direction: [
+                direction.x,
+                direction.y,
+                direction.z,
+                light.range.unwrap_or(0.0),
+            ],
+            color: [color.x, color.y, color.z, 0.0],
+            cone,
+        }
+    }
+}
+
+/// Uniform buffer holding the lights of the scene.
+pub struct LightBuffer {
+    ubo: UniformBufferObject<'static>,
+}
+
+impl LightBuffer {
+    pub fn new(gl: &gl::Gl) -> LightBuffer {
+        let lights_size = (MAX_LIGHTS * std::mem::size_of::<LightData>()) as isize;
+        let count_size = std::mem::size_of::<i32>() as isize;
+
+        LightBuffer {
+            ubo: UniformBufferObject::new(
+                gl,
+                1,
+                &[("lights", lights_size), ("lightCount", count_size)],
+            ),
+        }
+    }
+
+    /// Uploads the lights, any past `MAX_LIGHTS` are ignored.
+    pub fn write(&self, lights: &[Light]) {
+        let mut data: Vec<LightData> = lights.iter().take(MAX_LIGHTS).map(LightData::new).collect();
+        let count = data.len() as i32;
+
+        data.resize_with(MAX_LIGHTS, LightData::default);
+
+        self.ubo.write_sub_data("lights", data.as_ptr().cast());
+        self.ubo
+            .write_sub_data("lightCount", (&count as *const i32).cast());
+    }
+}
@@ -1,37 +1,242 @@
+use std::rc::Rc;
+
+use cgmath::{Matrix3, Vector2, Vector3, Vector4};
+
 use crate::{
     shader::Program,
     texture::Texture,
-    uniform::{Uniform, UniformInt},
+    uniform::{Uniform, Uniform3f, Uniform4f, UniformFloat, UniformInt, UniformMat3f},
 };
 
+/// Number of texture coordinate sets a mesh vertex carries.
+pub const UV_SETS: u32 = 2;
+
+/// Textures of a material. Loaders use other texture types, like indices of decoded images,
+/// before the textures are uploaded.
+pub struct MaterialTextures<T = Rc<Texture>> {
+    pub base_color: T,
+    pub metallic_roughness: T,
+    pub normal: T,
+    pub occlusion: T,
+    pub emissive: T,
+    pub transforms: TextureTransforms,
+}
+
+/// Texture coordinate set a texture is sampled with and the transform applied to it, as
+/// defined by KHR_texture_transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureTransform {
+    pub uv_set: u32,
+    pub offset: Vector2<f32>,
+    /// Counter-clockwise rotation of the texture coordinates around the origin, in radians.
+    pub rotation: f32,
+    pub scale: Vector2<f32>,
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        TextureTransform {
+            uv_set: 0,
+            offset: Vector2::new(0.0, 0.0),
+            rotation: 0.0,
+            scale: Vector2::new(1.0, 1.0),
+        }
+    }
+}
+
+impl TextureTransform {
+    /// Whether the texture coordinates are left unchanged, regardless of the UV set.
+    pub fn is_identity(&self) -> bool {
+        let identity = TextureTransform::default();
+
+        self.offset == identity.offset
+            && self.rotation == identity.rotation
+            && self.scale == identity.scale
+    }
+
+    /// Translation times rotation times scale, applied to the texture coordinates.
+    pub fn matrix(&self) -> Matrix3<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (scale, offset) = (self.scale, self.offset);
+
+        Matrix3::new(
+            cos * scale.x,
+            -sin * scale.x,
+            0.0,
+            sin * scale.y,
+            cos * scale.y,
+            0.0,
+            offset.x,
+            offset.y,
+            1.0,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextureTransforms {
+    pub base_color: TextureTransform,
+    pub metallic_roughness: TextureTransform,
+    pub normal: TextureTransform,
+    pub occlusion: TextureTransform,
+    pub emissive: TextureTransform,
+}
+
+/// Scalar parameters of the glTF metallic-roughness material model, which scale the values
+/// sampled from the textures.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialFactors {
+    pub base_color: Vector4<f32>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub emissive: Vector3<f32>,
+}
+
+impl Default for MaterialFactors {
+    fn default() -> Self {
+        MaterialFactors {
+            base_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            metallic: 1.0,
+            roughness: 1.0,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            emissive: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is ignored and the surface is fully opaque.
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded, the rest are opaque.
+    Mask { cutoff: f32 },
+    /// The surface is blended over what is behind it.
+    Blend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullMode {
+    Back,
+    Disabled,
+}
+
 pub struct Material<'a> {
     program: &'a Program,
-    base_color: Texture,
-    normal: Texture,
+    textures: MaterialTextures,
+    factors: MaterialFactors,
+    alpha_mode: AlphaMode,
+    cull_mode: CullMode,
 }
 
 impl<'a> Material<'a> {
-    pub fn new(program: &Program, base_color: Texture, normal: Texture) -> Material {
+    pub fn new(
+        program: &'a Program,
+        textures: MaterialTextures,
+        factors: MaterialFactors,
+        alpha_mode: AlphaMode,
+        cull_mode: CullMode,
+    ) -> Material<'a> {
         Material {
             program,
-            base_color,
-            normal,
+            textures,
+            factors,
+            alpha_mode,
+            cull_mode,
         }
     }
 
+    pub fn program(&self) -> &Program {
+        self.program
+    }
+
+    pub fn textures(&self) -> &MaterialTextures {
+        &self.textures
+    }
+
+    pub fn factors(&self) -> &MaterialFactors {
+        &self.factors
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    pub fn cull_mode(&self) -> CullMode {
+        self.cull_mode
+    }
+
     pub fn use_material(&self, uniforms: &Vec<Box<dyn Uniform>>) {
         self.program.use_program();
         self.set_textures();
+        self.set_factors();
+        self.set_alpha_mode();
         self.program.set_uniforms(uniforms)
     }
 
     fn set_textures(&self) {
-        self.base_color.bind(gl::TEXTURE0);
-        self.normal.bind(gl::TEXTURE1);
+        self.textures.base_color.bind(gl::TEXTURE0);
+        self.textures.metallic_roughness.bind(gl::TEXTURE1);
+        self.textures.normal.bind(gl::TEXTURE2);
+        self.textures.occlusion.bind(gl::TEXTURE3);
+        self.textures.emissive.bind(gl::TEXTURE4);
+
+        let transforms = &self.textures.transforms;
+
+        let uniforms: Vec<Box<dyn Uniform>> = vec![
+            UniformInt::new("baseColorTexture", 0),
+            UniformInt::new("metallicRoughnessTexture", 1),
+            UniformInt::new("normalTexture", 2),
+            UniformInt::new("occlusionTexture", 3),
+            UniformInt::new("emissiveTexture", 4),
+            UniformInt::new("baseColorUvSet", transforms.base_color.uv_set as i32),
+            UniformInt::new(
+                "metallicRoughnessUvSet",
+                transforms.metallic_roughness.uv_set as i32,
+            ),
+            UniformInt::new("normalUvSet", transforms.normal.uv_set as i32),
+            UniformInt::new("occlusionUvSet", transforms.occlusion.uv_set as i32),
+            UniformInt::new("emissiveUvSet", transforms.emissive.uv_set as i32),
+            UniformMat3f::new("baseColorUvTransform", transforms.base_color.matrix()),
+            UniformMat3f::new(
+                "metallicRoughnessUvTransform",
+                transforms.metallic_roughness.matrix(),
+            ),
+            UniformMat3f::new("normalUvTransform", transforms.normal.matrix()),
+            UniformMat3f::new("occlusionUvTransform", transforms.occlusion.matrix()),
+            UniformMat3f::new("emissiveUvTransform", transforms.emissive.matrix()),
+        ];
+
+        self.program.set_uniforms(&uniforms);
+    }
+
+    fn set_factors(&self) {
+        let factors = &self.factors;
+
+        let uniforms: Vec<Box<dyn Uniform>> = vec![
+            Uniform4f::new("baseColorFactor", factors.base_color),
+            UniformFloat::new("metallicFactor", factors.metallic),
+            UniformFloat::new("roughnessFactor", factors.roughness),
+            UniformFloat::new("normalScale", factors.normal_scale),
+            UniformFloat::new("occlusionStrength", factors.occlusion_strength),
+            Uniform3f::new("emissiveFactor", factors.emissive),
+        ];
+
+        self.program.set_uniforms(&uniforms);
+    }
+
+    fn set_alpha_mode(&self) {
+        let (alpha_mode, alpha_cutoff) = match self.alpha_mode {
+            AlphaMode::Opaque => (0, 0.0),
+            AlphaMode::Mask { cutoff } => (1, cutoff),
+            AlphaMode::Blend => (2, 0.0),
+        };
 
         let uniforms: Vec<Box<dyn Uniform>> = vec![
-            UniformInt::new("diffuseTexture", 0),
-            UniformInt::new("normalTexture", 1),
+            UniformInt::new("alphaMode", alpha_mode),
+            UniformFloat::new("alphaCutoff", alpha_cutoff),
         ];
 
         self.program.set_uniforms(&uniforms);
@@ -0,0 +1,331 @@
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector2, Vector3, Vector4, Zero};
+
+/// Expands indexed vertex data so that every triangle corner gets its own vertex. Like the
+/// other generators it expects the indices to be in range, importers validate them first.
+pub fn unweld<T: Copy>(data: &[T], indices: &[u32]) -> Vec<T> {
+    indices.iter().map(|index| data[*index as usize]).collect()
+}
+
+/// Computes flat normals for unwelded triangles, one normal per triangle corner.
+pub fn flat_normals(positions: &[Point3<f32>]) -> Vec<Vector3<f32>> {
+    positions
+        .chunks_exact(3)
+        .flat_map(|triangle| {
+            let normal = normalize_or(
+                face_normal(triangle[0], triangle[1], triangle[2]),
+                Vector3::unit_y(),
+            );
+            [normal; 3]
+        })
+        .collect()
+}
+
+/// Computes smooth normals by summing the normals of the triangles sharing each vertex,
+/// weighted by the area of the triangles.
+pub fn smooth_normals(positions: &[Point3<f32>], indices: &[u32]) -> Vec<Vector3<f32>> {
+    let mut normals = vec![Vector3::zero(); positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+
+        // The length of the face normal is twice the area of the triangle
+        let normal = face_normal(positions[i0], positions[i1], positions[i2]);
+
+        for index in [i0, i1, i2] {
+            normals[index] += normal;
+        }
+    }
+
+    normals
+        .into_iter()
+        .map(|normal| normalize_or(normal, Vector3::unit_y()))
+        .collect()
+}
+
+/// Generates per-vertex tangents with MikkTSpace, the tangent space Blender and Substance
+/// bake normal maps against and the one glTF asks for when tangents are missing. `w` holds
+/// the handedness so that `bitangent = cross(normal, tangent.xyz) * w`, as in glTF.
+///
+/// UVs are expected to have their origin at the top left corner, like in glTF, so the
+/// bitangent points towards decreasing `v`. They are flipped to the bottom left origin
+/// MikkTSpace is defined with, the same way glTF exporters flip Blender UVs.
+///
+/// MikkTSpace can give a vertex shared by several triangles a different tangent in each of
+/// them, while the vertex keeps the one of its last triangle. Vertices without usable UVs
+/// get any direction perpendicular to their normal.
+pub fn tangents(
+    positions: &[Point3<f32>],
+    normals: &[Vector3<f32>],
+    uvs: &[Vector2<f32>],
+    indices: &[u32],
+) -> Vec<Vector4<f32>> {
+    let mut geometry = TangentGeometry {
+        positions,
+        normals,
+        uvs,
+        indices,
+        tangents: vec![None; positions.len()],
+    };
+    bevy_mikktspace::generate_tangents(&mut geometry);
+
+    normals
+        .iter()
+        .zip(geometry.tangents)
+        .map(|(normal, tangent)| match tangent {
+            Some(tangent) if tangent.truncate().magnitude2() > f32::EPSILON => tangent,
+            _ => perpendicular(*normal).extend(1.0),
+        })
+        .collect()
+}
+
+/// Indexed triangles seen through the interface of the MikkTSpace generator.
+struct TangentGeometry<'a> {
+    positions: &'a [Point3<f32>],
+    normals: &'a [Vector3<f32>],
+    uvs: &'a [Vector2<f32>],
+    indices: &'a [u32],
+    tangents: Vec<Option<Vector4<f32>>>,
+}
+
+impl TangentGeometry<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        self.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.positions[self.index(face, vert)].into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.normals[self.index(face, vert)].into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let uv = self.uvs[self.index(face, vert)];
+        [uv.x, 1.0 - uv.y]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.index(face, vert);
+        self.tangents[index] = Some(tangent.into());
+    }
+}
+
+/// Computes the bitangent from a normal and a tangent with its handedness in `w`.
+pub fn bitangent(normal: Vector3<f32>, tangent: Vector4<f32>) -> Vector3<f32> {
+    normal.cross(tangent.truncate()) * tangent.w
+}
+
+/// Computes the center of the bounding box of the given positions.
+pub fn bounds_center(positions: &[Point3<f32>]) -> Point3<f32> {
+    let Some(first) = positions.first() else {
+        return Point3::origin();
+    };
+
+    let (min, max) = positions
+        .iter()
+        .fold((*first, *first), |(min, max), position| {
+            (
+                Point3::new(
+                    min.x.min(position.x),
+                    min.y.min(position.y),
+                    min.z.min(position.z),
+                ),
+                Point3::new(
+                    max.x.max(position.x),
+                    max.y.max(position.y),
+                    max.z.max(position.z),
+                ),
+            )
+        });
+
+    min.midpoint(max)
+}
+
+fn face_normal(p0: Point3<f32>, p1: Point3<f32>, p2: Point3<f32>) -> Vector3<f32> {
+    (p1 - p0).cross(p2 - p0)
+}
+
+fn perpendicular(normal: Vector3<f32>) -> Vector3<f32> {
+    let axis = match normal.x.abs() < 0.9 {
+        true => Vector3::unit_x(),
+        false => Vector3::unit_y(),
+    };
+
+    normalize_or(axis.cross(normal), Vector3::unit_z())
+}
+
+/// Normalizes the vector, returning `fallback` for zero length vectors.
+pub fn normalize_or(vector: Vector3<f32>, fallback: Vector3<f32>) -> Vector3<f32> {
+    match vector.magnitude2() > 0.0 {
+        true => vector.normalize(),
+        false => fallback,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tangents of a quad facing +Z, with the given UV for each corner.
+    fn quad_tangents(uvs: [[f32; 2]; 4]) -> Vec<Vector4<f32>> {
+        let positions = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let normals = [Vector3::unit_z(); 4];
+        let uvs = uvs.map(Vector2::from);
+
+        tangents(&positions, &normals, &uvs, &[0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn tangents_follow_u() {
+        let tangents = quad_tangents([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+
+        for tangent in tangents {
+            assert_eq!(tangent, Vector4::new(1.0, 0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_handedness() {
+        let tangents = quad_tangents([[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]);
+
+        for tangent in tangents {
+            assert_eq!(tangent, Vector4::new(-1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn bitangent_points_towards_decreasing_v() {
+        let normal = Vector3::unit_z();
+        let tangent = quad_tangents([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]])[0];
+
+        // v decreases along +Y on this quad
+        assert_eq!(bitangent(normal, tangent), Vector3::unit_y());
+    }
+
+    /// Tangents of the cube of the MikkTSpace regression test, with every side split into
+    /// four triangles around its center and the normals pointing away from the center.
+    fn reference_cube_tangents() -> Vec<Vector4<f32>> {
+        let sides: [[([f32; 2], [f32; 3]); 5]; 6] = [
+            [
+                ([0.0, 0.0], [1.0, -1.0, 1.0]),
+                ([0.0, 1.0], [1.0, -1.0, -1.0]),
+                ([1.0, 1.0], [1.0, 1.0, -1.0]),
+                ([1.0, 0.0], [1.0, 1.0, 1.0]),
+                ([0.5, 0.5], [1.0, 0.0, 0.0]),
+            ],
+            [
+                ([1.0, 0.0], [-1.0, 1.0, 1.0]),
+                ([1.0, 1.0], [-1.0, 1.0, -1.0]),
+                ([0.0, 1.0], [-1.0, -1.0, -1.0]),
+                ([0.0, 0.0], [-1.0, -1.0, 1.0]),
+                ([0.5, 0.5], [-1.0, 0.0, 0.0]),
+            ],
+            [
+                ([0.0, 0.0], [1.0, 1.0, 1.0]),
+                ([0.0, 1.0], [1.0, 1.0, -1.0]),
+                ([0.0, 1.0], [-1.0, 1.0, -1.0]),
+                ([0.0, 0.0], [-1.0, 1.0, 1.0]),
+                ([0.0, 0.5], [0.0, 1.0, 0.0]),
+            ],
+            [
+                ([0.0, 0.0], [-1.0, -1.0, 1.0]),
+                ([0.0, 1.0], [-1.0, -1.0, -1.0]),
+                ([0.0, 1.0], [1.0, -1.0, -1.0]),
+                ([0.0, 0.0], [1.0, -1.0, 1.0]),
+                ([0.0, 0.5], [0.0, -1.0, 0.0]),
+            ],
+            [
+                ([0.0, 0.0], [-1.0, 1.0, 1.0]),
+                ([0.0, 1.0], [-1.0, -1.0, 1.0]),
+                ([1.0, 1.0], [1.0, -1.0, 1.0]),
+                ([1.0, 0.0], [1.0, 1.0, 1.0]),
+                ([0.5, 0.5], [0.0, 0.0, 1.0]),
+            ],
+            [
+                ([1.0, 0.0], [1.0, 1.0, -1.0]),
+                ([1.0, 1.0], [1.0, -1.0, -1.0]),
+                ([0.0, 1.0], [-1.0, -1.0, -1.0]),
+                ([0.0, 0.0], [-1.0, 1.0, -1.0]),
+                ([0.5, 0.5], [0.0, 0.0, -1.0]),
+            ],
+        ];
+
+        let corners = sides.iter().flatten();
+        let positions: Vec<_> = corners
+            .clone()
+            .map(|(_, d)| Point3::from(*d) / 2.0)
+            .collect();
+        let normals: Vec<_> = corners
+            .clone()
+            .map(|(_, d)| Vector3::from(*d).normalize())
+            .collect();
+        // The regression test uses bottom left UVs, flipped back by the generator
+        let uvs: Vec<_> = corners
+            .map(|(uv, _)| Vector2::new(uv[0], 1.0 - uv[1]))
+            .collect();
+        let indices: Vec<_> = (0..6)
+            .flat_map(|side| {
+                let base = side * 5;
+                [0, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4].map(|corner| base + corner)
+            })
+            .collect();
+
+        tangents(&positions, &normals, &uvs, &indices)
+    }
+
+    #[test]
+    fn matches_reference_mikktspace_tangents() {
+        let tangents = reference_cube_tangents();
+
+        // Reference values of the +X and -X sides, whose corners get the same tangent in
+        // every triangle
+        let (a, b) = (0.40824825, 0.81649655);
+        let expected = [
+            Vector4::new(a, b, a, -1.0),
+            Vector4::new(a, b, -a, -1.0),
+            Vector4::new(-a, b, a, -1.0),
+            Vector4::new(-a, b, -a, -1.0),
+            Vector4::new(0.0, 1.0, 0.0, -1.0),
+            Vector4::new(a, b, -a, 1.0),
+            Vector4::new(a, b, a, 1.0),
+            Vector4::new(-a, b, -a, 1.0),
+            Vector4::new(-a, b, a, 1.0),
+            Vector4::new(0.0, 1.0, 0.0, 1.0),
+        ];
+
+        for (tangent, expected) in tangents.iter().zip(expected) {
+            assert!(
+                (tangent - expected).magnitude() < 1e-6,
+                "{:?} != {:?}",
+                tangent,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_give_a_perpendicular_tangent() {
+        let tangents = quad_tangents([[0.5, 0.5]; 4]);
+
+        for tangent in tangents {
+            assert_eq!(tangent.truncate().dot(Vector3::unit_z()), 0.0);
+            assert_eq!(tangent.truncate().magnitude(), 1.0);
+        }
+    }
+}
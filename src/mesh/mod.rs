@@ -1,9 +1,35 @@
+pub mod generate;
+mod morph;
 mod vertex;
 
-pub use self::vertex::{MeshVertex, Vertex};
+use cgmath::Point3;
+
+pub use self::morph::{MorphTarget, MorphTargets};
+pub use self::vertex::{MeshVertex, SkinnedMeshVertex, Vertex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    Triangles,
+    Points,
+}
+
+impl Topology {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::Points => gl::POINTS,
+        }
+    }
+}
 
 pub struct Mesh {
     gl: gl::Gl,
+    topology: Topology,
+    vertex_count: usize,
+    vertex_size: usize,
+    /// Center of the bounding box, used to sort transparent meshes by distance.
+    center: Point3<f32>,
+    morph_targets: Option<MorphTargets>,
 
     pub indices: Vec<i32>,
 
@@ -14,6 +40,15 @@ pub struct Mesh {
 
 impl Mesh {
     pub fn create<T: Vertex>(gl: &gl::Gl, vertices: Vec<T>, indices: Vec<i32>) -> Mesh {
+        Mesh::create_with_topology(gl, vertices, indices, Topology::Triangles)
+    }
+
+    pub fn create_with_topology<T: Vertex>(
+        gl: &gl::Gl,
+        vertices: Vec<T>,
+        indices: Vec<i32>,
+        topology: Topology,
+    ) -> Mesh {
         let mut vao = 0;
         unsafe { gl.GenVertexArrays(1, &mut vao) };
 
@@ -44,8 +79,15 @@ impl Mesh {
             T::set_vertex_attrib_pointer(gl);
         }
 
+        let positions: Vec<Point3<f32>> = vertices.iter().map(Vertex::position).collect();
+
         Mesh {
             gl: gl.clone(),
+            topology,
+            vertex_count: vertices.len(),
+            vertex_size: std::mem::size_of::<T>(),
+            center: generate::bounds_center(&positions),
+            morph_targets: None,
 
             indices,
 
@@ -55,6 +97,48 @@ impl Mesh {
         }
     }
 
+    pub fn with_morph_targets(mut self, morph_targets: MorphTargets) -> Mesh {
+        self.morph_targets = Some(morph_targets);
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.center
+    }
+
+    pub fn morph_targets(&self) -> Option<&MorphTargets> {
+        self.morph_targets.as_ref()
+    }
+
+    /// Reads the vertices back from the GPU, `T` must be the vertex type the mesh was
+    /// created with.
+    pub fn read_vertices<T: Vertex + Copy>(&self) -> Vec<T> {
+        assert_eq!(
+            std::mem::size_of::<T>(),
+            self.vertex_size,
+            "mesh was created with a different vertex type"
+        );
+
+        let mut vertices: Vec<T> = Vec::with_capacity(self.vertex_count);
+
+        unsafe {
+            self.gl.BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            self.gl.GetBufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                (self.vertex_size * self.vertex_count) as isize,
+                vertices.as_mut_ptr().cast(),
+            );
+            vertices.set_len(self.vertex_count);
+        }
+
+        vertices
+    }
+
     pub fn draw(&self) {
         unsafe {
             self.gl.BindVertexArray(self.vao);
@@ -62,7 +146,7 @@ impl Mesh {
             self.gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
 
             self.gl.DrawElements(
-                gl::TRIANGLES,
+                self.topology.to_gl(),
                 self.indices.len() as i32,
                 gl::UNSIGNED_INT,
                 std::ptr::null(),
@@ -70,3 +154,12 @@ impl Mesh {
         };
     }
 }
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteVertexArrays(1, [self.vao].as_ptr());
+            self.gl.DeleteBuffers(2, [self.vbo, self.ebo].as_ptr());
+        }
+    }
+}
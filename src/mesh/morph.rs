@@ -0,0 +1,111 @@
+use cgmath::Vector3;
+
+/// Displacements added to the vertices of a mesh, one per vertex for every attribute.
+pub struct MorphTarget {
+    pub positions: Vec<Vector3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub tangents: Vec<Vector3<f32>>,
+}
+
+/// Morph targets of a mesh stored in a buffer texture, so the vertex shader can blend any
+/// number of them. Every vertex stores the position, normal and tangent displacements of
+/// each target one after the other.
+pub struct MorphTargets {
+    gl: gl::Gl,
+    buffer: gl::types::GLuint,
+    texture: gl::types::GLuint,
+    count: usize,
+    vertex_count: usize,
+}
+
+impl MorphTargets {
+    pub fn new(gl: &gl::Gl, targets: &[MorphTarget]) -> MorphTargets {
+        let vertex_count = targets.first().map_or(0, |target| target.positions.len());
+
+        let mut displacements = Vec::with_capacity(vertex_count * targets.len() * 3);
+        for vertex in 0..vertex_count {
+            for target in targets {
+                displacements.push(target.positions[vertex]);
+                displacements.push(target.normals[vertex]);
+                displacements.push(target.tangents[vertex]);
+            }
+        }
+
+        let mut buffer = 0;
+        let mut texture = 0;
+
+        unsafe {
+            gl.GenBuffers(1, &mut buffer);
+            gl.BindBuffer(gl::TEXTURE_BUFFER, buffer);
+            gl.BufferData(
+                gl::TEXTURE_BUFFER,
+                (std::mem::size_of::<Vector3<f32>>() * displacements.len()) as isize,
+                displacements.as_ptr().cast(),
+                gl::STATIC_DRAW,
+            );
+
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(gl::TEXTURE_BUFFER, texture);
+            gl.TexBuffer(gl::TEXTURE_BUFFER, gl::RGB32F, buffer);
+        }
+
+        MorphTargets {
+            gl: gl.clone(),
+            buffer,
+            texture,
+            count: targets.len(),
+            vertex_count,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Reads the displacements back from the GPU.
+    pub fn read_targets(&self) -> Vec<MorphTarget> {
+        let length = self.vertex_count * self.count * 3;
+        let mut displacements: Vec<Vector3<f32>> = Vec::with_capacity(length);
+
+        unsafe {
+            self.gl.BindBuffer(gl::TEXTURE_BUFFER, self.buffer);
+            self.gl.GetBufferSubData(
+                gl::TEXTURE_BUFFER,
+                0,
+                (std::mem::size_of::<Vector3<f32>>() * length) as isize,
+                displacements.as_mut_ptr().cast(),
+            );
+            displacements.set_len(length);
+        }
+
+        (0..self.count)
+            .map(|target| {
+                let displacement = |vertex: usize, attribute: usize| {
+                    displacements[(vertex * self.count + target) * 3 + attribute]
+                };
+
+                MorphTarget {
+                    positions: (0..self.vertex_count).map(|v| displacement(v, 0)).collect(),
+                    normals: (0..self.vertex_count).map(|v| displacement(v, 1)).collect(),
+                    tangents: (0..self.vertex_count).map(|v| displacement(v, 2)).collect(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn bind(&self, slot: gl::types::GLenum) {
+        unsafe {
+            self.gl.ActiveTexture(slot);
+            self.gl.BindTexture(gl::TEXTURE_BUFFER, self.texture);
+        }
+    }
+}
+
+impl Drop for MorphTargets {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, [self.texture].as_ptr());
+            self.gl.DeleteBuffers(1, [self.buffer].as_ptr());
+        }
+    }
+}
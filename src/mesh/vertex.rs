@@ -2,6 +2,8 @@ use cgmath::*;
 
 pub trait Vertex {
     fn set_vertex_attrib_pointer(gl: &gl::Gl);
+
+    fn position(&self) -> Point3<f32>;
 }
 
 #[derive(Clone, Copy)]
@@ -12,6 +14,10 @@ pub struct MeshVertex {
     normal: Vector3<f32>,
     tangent: Vector3<f32>,
     bitangent: Vector3<f32>,
+    /// Linear color multiplied with the base color of the material.
+    color: Vector4<f32>,
+    /// Second texture coordinate set, often used by lightmaps and occlusion maps.
+    uv1: Vector2<f32>,
 }
 
 impl MeshVertex {
@@ -26,20 +32,59 @@ impl MeshVertex {
         tangent: U,
         bitangent: U,
     ) -> MeshVertex {
+        let uv = uv.into();
+
         MeshVertex {
             position: position.into(),
-            uv: uv.into(),
+            uv,
             normal: normal.into(),
             tangent: tangent.into(),
             bitangent: bitangent.into(),
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            uv1: uv,
         }
     }
-}
 
-impl Vertex for MeshVertex {
-    fn set_vertex_attrib_pointer(gl: &gl::Gl) {
-        let stride = 14 * std::mem::size_of::<f32>() as i32;
+    pub fn with_color<C: Into<cgmath::Vector4<f32>>>(self, color: C) -> MeshVertex {
+        MeshVertex {
+            color: color.into(),
+            ..self
+        }
+    }
 
+    /// Replaces the second texture coordinate set, which defaults to the first one.
+    pub fn with_uv1<V: Into<cgmath::Vector2<f32>>>(self, uv1: V) -> MeshVertex {
+        MeshVertex {
+            uv1: uv1.into(),
+            ..self
+        }
+    }
+
+    pub fn uv(&self) -> Vector2<f32> {
+        self.uv
+    }
+
+    pub fn uv1(&self) -> Vector2<f32> {
+        self.uv1
+    }
+
+    pub fn normal(&self) -> Vector3<f32> {
+        self.normal
+    }
+
+    pub fn tangent(&self) -> Vector3<f32> {
+        self.tangent
+    }
+
+    pub fn bitangent(&self) -> Vector3<f32> {
+        self.bitangent
+    }
+
+    pub fn color(&self) -> Vector4<f32> {
+        self.color
+    }
+
+    fn set_attrib_pointers(gl: &gl::Gl, stride: i32) {
         unsafe {
             gl.VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
             gl.VertexAttribPointer(
@@ -74,12 +119,104 @@ impl Vertex for MeshVertex {
                 stride,
                 (11 * std::mem::size_of::<f32>()) as *const _,
             );
+            gl.VertexAttribPointer(
+                7,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (14 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl.VertexAttribPointer(
+                8,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (18 * std::mem::size_of::<f32>()) as *const _,
+            );
 
             gl.EnableVertexAttribArray(0);
             gl.EnableVertexAttribArray(1);
             gl.EnableVertexAttribArray(2);
             gl.EnableVertexAttribArray(3);
             gl.EnableVertexAttribArray(4);
+            gl.EnableVertexAttribArray(7);
+            gl.EnableVertexAttribArray(8);
+        }
+    }
+}
+
+impl Vertex for MeshVertex {
+    fn set_vertex_attrib_pointer(gl: &gl::Gl) {
+        MeshVertex::set_attrib_pointers(gl, std::mem::size_of::<MeshVertex>() as i32);
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.position
+    }
+}
+
+/// Mesh vertex influenced by up to four joints of a skin.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct SkinnedMeshVertex {
+    vertex: MeshVertex,
+    joints: [u32; 4],
+    weights: Vector4<f32>,
+}
+
+impl SkinnedMeshVertex {
+    pub fn new<T: Into<cgmath::Vector4<f32>>>(
+        vertex: MeshVertex,
+        joints: [u32; 4],
+        weights: T,
+    ) -> SkinnedMeshVertex {
+        SkinnedMeshVertex {
+            vertex,
+            joints,
+            weights: weights.into(),
         }
     }
+
+    pub fn vertex(&self) -> MeshVertex {
+        self.vertex
+    }
+
+    pub fn joints(&self) -> [u32; 4] {
+        self.joints
+    }
+
+    pub fn weights(&self) -> Vector4<f32> {
+        self.weights
+    }
+}
+
+impl Vertex for SkinnedMeshVertex {
+    fn set_vertex_attrib_pointer(gl: &gl::Gl) {
+        let stride = std::mem::size_of::<SkinnedMeshVertex>() as i32;
+        let joints_offset = std::mem::size_of::<MeshVertex>();
+        let weights_offset = joints_offset + 4 * std::mem::size_of::<u32>();
+
+        MeshVertex::set_attrib_pointers(gl, stride);
+
+        unsafe {
+            gl.VertexAttribIPointer(5, 4, gl::UNSIGNED_INT, stride, joints_offset as *const _);
+            gl.VertexAttribPointer(
+                6,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                weights_offset as *const _,
+            );
+
+            gl.EnableVertexAttribArray(5);
+            gl.EnableVertexAttribArray(6);
+        }
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.vertex.position()
+    }
 }
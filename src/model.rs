@@ -1,21 +1,434 @@
-use crate::{material::Material, mesh::Mesh, uniform::Uniform};
+use std::rc::Rc;
+
+use cgmath::{Matrix, Matrix4, MetricSpace, Point3, Quaternion, SquareMatrix, Transform, Vector3};
+
+use crate::{
+    animation::{Animation, NodePose},
+    camera::{Camera, Projection},
+    light::Light,
+    material::{AlphaMode, CullMode, Material},
+    mesh::{Mesh, MorphTargets},
+    uniform::{Uniform, UniformFloatArray, UniformInt, UniformMat4f},
+    uniform_buffer_object::UniformBufferObject,
+};
+
+/// Maximum number of joints in a skin, must match the size of the joint matrices array in
+/// the vertex shader.
+pub const MAX_JOINTS: usize = 128;
+
+/// Maximum number of morph targets of a primitive, must match the size of the morph weights
+/// array in the vertex shader.
+pub const MAX_MORPH_TARGETS: usize = 64;
+
+pub struct Primitive {
+    /// Shared with other primitives and models loading the same asset.
+    mesh: Rc<Mesh>,
+    material_index: usize,
+    /// Whether the vertices carry joints and weights.
+    skinned: bool,
+}
+
+impl Primitive {
+    pub fn new(mesh: Rc<Mesh>, material_index: usize, skinned: bool) -> Primitive {
+        Primitive {
+            mesh,
+            material_index,
+            skinned,
+        }
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    pub fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        self.skinned
+    }
+
+    pub fn morph_targets(&self) -> Option<&MorphTargets> {
+        self.mesh.morph_targets()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+    pub children: Vec<usize>,
+
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+    /// Morph target weights of the mesh, as set by animations or user code. Missing weights
+    /// are treated as zero.
+    pub weights: Vec<f32>,
+
+    /// Camera looking down the -Z axis of the node.
+    pub camera: Option<Projection>,
+    /// Light placed in the local space of the node.
+    pub light: Option<Light>,
+}
+
+impl Node {
+    pub fn new(
+        mesh: Option<usize>,
+        skin: Option<usize>,
+        children: Vec<usize>,
+        translation: Vector3<f32>,
+        rotation: Quaternion<f32>,
+        scale: Vector3<f32>,
+        weights: Vec<f32>,
+    ) -> Node {
+        Node {
+            mesh,
+            skin,
+            children,
+            translation,
+            rotation,
+            scale,
+            weights,
+            camera: None,
+            light: None,
+        }
+    }
+
+    pub fn pose(&self) -> NodePose {
+        NodePose {
+            translation: self.translation,
+            rotation: self.rotation,
+            scale: self.scale,
+            weights: self.weights.clone(),
+        }
+    }
+
+    pub fn set_pose(&mut self, pose: NodePose) {
+        self.translation = pose.translation;
+        self.rotation = pose.rotation;
+        self.scale = pose.scale;
+        self.weights = pose.weights;
+    }
+
+    pub fn local_transform(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+/// Joints deforming a skinned mesh, given as node indices, along with the matrices that
+/// transform the mesh into the local space of each joint in the bind pose.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Skin {
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+impl Skin {
+    pub fn new(joints: Vec<usize>, inverse_bind_matrices: Vec<Matrix4<f32>>) -> Skin {
+        Skin {
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+}
 
 pub struct Model<'a> {
-    meshes: Vec<(Mesh, i32)>,
+    gl: gl::Gl,
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    meshes: Vec<Vec<Primitive>>,
     materials: Vec<Material<'a>>,
+    skins: Vec<Skin>,
+    animations: Vec<Animation>,
+    joint_matrices_ubo: UniformBufferObject<'static>,
 }
 
 impl<'a> Model<'a> {
-    pub fn new(meshes: Vec<(Mesh, i32)>, materials: Vec<Material>) -> Model {
-        Model { meshes, materials }
+    pub fn new(
+        gl: &gl::Gl,
+        nodes: Vec<Node>,
+        roots: Vec<usize>,
+        meshes: Vec<Vec<Primitive>>,
+        materials: Vec<Material<'a>>,
+        skins: Vec<Skin>,
+        animations: Vec<Animation>,
+    ) -> Model<'a> {
+        // Every model gets its own buffer, so the joint matrices block is backed even when
+        // drawing models without skins
+        let joint_matrices_size = (MAX_JOINTS * std::mem::size_of::<Matrix4<f32>>()) as isize;
+        let joint_matrices_ubo =
+            UniformBufferObject::new(gl, 2, &[("jointMatrices", joint_matrices_size)]);
+
+        Model {
+            gl: gl.clone(),
+            nodes,
+            roots,
+            meshes,
+            materials,
+            skins,
+            animations,
+            joint_matrices_ubo,
+        }
+    }
+
+    /// Model without nodes, drawing nothing. Used as a placeholder while loading.
+    pub fn empty(gl: &gl::Gl) -> Model<'a> {
+        Model::new(
+            gl,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    pub fn meshes(&self) -> &[Vec<Primitive>] {
+        &self.meshes
+    }
+
+    pub fn materials(&self) -> &[Material<'a>] {
+        &self.materials
+    }
+
+    pub fn skins(&self) -> &[Skin] {
+        &self.skins
+    }
+
+    pub fn animations(&self) -> &[Animation] {
+        &self.animations
+    }
+
+    /// Local transforms and morph target weights of all nodes, indexed like the nodes.
+    pub fn pose(&self) -> Vec<NodePose> {
+        self.nodes.iter().map(Node::pose).collect()
+    }
+
+    pub fn set_pose(&mut self, pose: Vec<NodePose>) {
+        for (node, pose) in self.nodes.iter_mut().zip(pose) {
+            node.set_pose(pose);
+        }
+    }
+
+    /// Computes the world matrix of every node reachable from the roots, indexed like the
+    /// nodes. Nodes outside of the hierarchy are left as `None`.
+    pub fn world_transforms(&self, transform: Matrix4<f32>) -> Vec<Option<Matrix4<f32>>> {
+        let mut world_transforms = vec![None; self.nodes.len()];
+
+        let mut stack: Vec<(usize, Matrix4<f32>)> =
+            self.roots.iter().map(|root| (*root, transform)).collect();
+
+        while let Some((index, parent_transform)) = stack.pop() {
+            let node = &self.nodes[index];
+            let world_transform = parent_transform * node.local_transform();
+            world_transforms[index] = Some(world_transform);
+
+            stack.extend(node.children.iter().map(|child| (*child, world_transform)));
+        }
+
+        world_transforms
     }
 
+    /// Creates a camera for every node of the hierarchy with one, in node order.
+    pub fn cameras(&self, transform: Matrix4<f32>, aspect: f32) -> Vec<Camera> {
+        let world_transforms = self.world_transforms(transform);
+
+        self.nodes
+            .iter()
+            .zip(world_transforms)
+            .filter_map(
+                |(node, world_transform)| match (node.camera, world_transform) {
+                    (Some(projection), Some(world_transform)) => {
+                        Some(Camera::from_transform(world_transform, projection, aspect))
+                    }
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    /// Places the lights of the nodes of the hierarchy in world space, in node order.
+    pub fn lights(&self, transform: Matrix4<f32>) -> Vec<Light> {
+        let world_transforms = self.world_transforms(transform);
+
+        self.nodes
+            .iter()
+            .zip(world_transforms)
+            .filter_map(|(node, world_transform)| Some(node.light?.transformed(world_transform?)))
+            .collect()
+    }
+
+    /// Draws opaque and alpha tested primitives first, then blended primitives from back to
+    /// front as seen from `view_position`, so they blend over everything behind them.
     // TODO: Improve how we handle the uniforms here
-    pub fn draw(&self, uniforms: Vec<Box<dyn Uniform>>) {
-        for (mesh, material_index) in &self.meshes {
-            let material = self.materials.get(*material_index as usize).unwrap();
-            material.use_material(&uniforms);
-            mesh.draw();
+    pub fn draw(
+        &self,
+        transform: Matrix4<f32>,
+        view_position: Point3<f32>,
+        uniforms: Vec<Box<dyn Uniform>>,
+    ) {
+        let world_transforms = self.world_transforms(transform);
+
+        let joint_matrices: Vec<Option<Vec<Matrix4<f32>>>> = self
+            .nodes
+            .iter()
+            .zip(&world_transforms)
+            .map(
+                |(node, world_transform)| match (node.skin, world_transform) {
+                    (Some(skin), Some(world_transform)) => Some(self.joint_matrices(
+                        &self.skins[skin],
+                        *world_transform,
+                        &world_transforms,
+                    )),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        self.joint_matrices_ubo.bind();
+
+        unsafe {
+            self.gl.Disable(gl::BLEND);
+            self.gl.DepthMask(gl::TRUE);
+        }
+
+        let mut transparent_primitives = Vec::new();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let (Some(mesh_index), Some(model_matrix)) = (node.mesh, world_transforms[index])
+            else {
+                continue;
+            };
+            let joint_matrices = joint_matrices[index].as_deref();
+
+            for primitive in &self.meshes[mesh_index] {
+                let material = &self.materials[primitive.material_index];
+
+                match material.alpha_mode() {
+                    AlphaMode::Blend => {
+                        let center = model_matrix.transform_point(primitive.mesh.center());
+                        let distance = view_position.distance2(center);
+                        transparent_primitives.push((
+                            distance,
+                            primitive,
+                            model_matrix,
+                            joint_matrices,
+                            &node.weights,
+                        ));
+                    }
+                    _ => self.draw_primitive(
+                        primitive,
+                        model_matrix,
+                        joint_matrices,
+                        &node.weights,
+                        &uniforms,
+                    ),
+                }
+            }
+        }
+
+        transparent_primitives.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+
+        unsafe {
+            self.gl.Enable(gl::BLEND);
+            self.gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            self.gl.DepthMask(gl::FALSE);
+        }
+
+        for (_, primitive, model_matrix, joint_matrices, weights) in transparent_primitives {
+            self.draw_primitive(primitive, model_matrix, joint_matrices, weights, &uniforms);
+        }
+
+        unsafe {
+            self.gl.Disable(gl::BLEND);
+            self.gl.DepthMask(gl::TRUE);
+        }
+    }
+
+    /// Computes the joint matrices of a skin relative to the node of the skinned mesh, so the
+    /// mesh keeps using the model matrix of its node. The result is padded to `MAX_JOINTS`.
+    fn joint_matrices(
+        &self,
+        skin: &Skin,
+        mesh_transform: Matrix4<f32>,
+        world_transforms: &[Option<Matrix4<f32>>],
+    ) -> Vec<Matrix4<f32>> {
+        let inverse_mesh_transform = mesh_transform.invert().unwrap_or(Matrix4::identity());
+
+        let mut joint_matrices = vec![Matrix4::identity(); MAX_JOINTS];
+
+        let joints = skin.joints.iter().zip(&skin.inverse_bind_matrices);
+        for (joint_matrix, (joint, inverse_bind_matrix)) in joint_matrices.iter_mut().zip(joints) {
+            let joint_transform = world_transforms[*joint].unwrap_or(Matrix4::identity());
+            *joint_matrix = inverse_mesh_transform * joint_transform * inverse_bind_matrix;
         }
+
+        joint_matrices
+    }
+
+    fn draw_primitive(
+        &self,
+        primitive: &Primitive,
+        model_matrix: Matrix4<f32>,
+        joint_matrices: Option<&[Matrix4<f32>]>,
+        morph_weights: &[f32],
+        uniforms: &Vec<Box<dyn Uniform>>,
+    ) {
+        let material = &self.materials[primitive.material_index];
+
+        let normal_matrix = model_matrix
+            .invert()
+            .unwrap_or(Matrix4::identity())
+            .transpose();
+
+        let joint_matrices = joint_matrices.filter(|_| primitive.skinned);
+        if let Some(joint_matrices) = joint_matrices {
+            self.joint_matrices_ubo
+                .write_sub_data("jointMatrices", joint_matrices.as_ptr().cast());
+        }
+
+        let morph_target_count = match primitive.morph_targets() {
+            Some(morph_targets) => {
+                morph_targets.bind(gl::TEXTURE5);
+                morph_targets.count()
+            }
+            None => 0,
+        };
+        let morph_weights = (0..morph_target_count)
+            .map(|target| morph_weights.get(target).copied().unwrap_or(0.0))
+            .collect();
+
+        let node_uniforms: Vec<Box<dyn Uniform>> = vec![
+            UniformMat4f::new("model", model_matrix),
+            UniformMat4f::new("normalMatrix", normal_matrix),
+            UniformInt::new("skinned", joint_matrices.is_some() as i32),
+            UniformInt::new("morphTargets", 5),
+            UniformInt::new("morphTargetCount", morph_target_count as i32),
+            UniformFloatArray::new("morphWeights", morph_weights),
+        ];
+
+        unsafe {
+            match material.cull_mode() {
+                CullMode::Back => self.gl.Enable(gl::CULL_FACE),
+                CullMode::Disabled => self.gl.Disable(gl::CULL_FACE),
+            }
+        }
+
+        material.use_material(uniforms);
+        material.program().set_uniforms(&node_uniforms);
+        primitive.mesh.draw();
     }
 }
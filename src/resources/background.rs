@@ -0,0 +1,286 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::model::Model;
+use crate::shader::Program;
+use crate::texture::DefaultTextures;
+
+use super::{Error, ModelData, ModelUpload, ResourceLoader};
+
+pub enum LoadStatus {
+    Loading,
+    Ready,
+    /// The handle keeps its placeholder, or the previous asset when reloading.
+    Failed(Error),
+}
+
+struct HandleState<T> {
+    asset: RefCell<T>,
+    status: RefCell<LoadStatus>,
+    files: RefCell<Vec<PathBuf>>,
+}
+
+/// Shared handle to an asset loaded in the background. It holds a placeholder until the
+/// asset is ready, and a reloaded asset replaces the current one once it is ready.
+pub struct AssetHandle<T> {
+    state: Rc<HandleState<T>>,
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        AssetHandle {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> AssetHandle<T> {
+    fn new(placeholder: T) -> AssetHandle<T> {
+        AssetHandle {
+            state: Rc::new(HandleState {
+                asset: RefCell::new(placeholder),
+                status: RefCell::new(LoadStatus::Loading),
+                files: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    pub fn get(&self) -> Ref<'_, T> {
+        self.state.asset.borrow()
+    }
+
+    /// Borrows the asset mutably. The borrow has to end before `BackgroundLoader::upload`,
+    /// which replaces the asset when it is ready.
+    pub fn get_mut(&self) -> RefMut<'_, T> {
+        self.state.asset.borrow_mut()
+    }
+
+    pub fn status(&self) -> Ref<'_, LoadStatus> {
+        self.state.status.borrow()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.status(), LoadStatus::Loading)
+    }
+
+    /// Files the current asset was read from, empty until it is ready.
+    pub fn files(&self) -> Ref<'_, Vec<PathBuf>> {
+        self.state.files.borrow()
+    }
+
+    fn set_status(&self, status: LoadStatus) {
+        *self.state.status.borrow_mut() = status;
+    }
+
+    fn is_same(&self, other: &AssetHandle<T>) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+/// Model to read on a worker thread.
+struct Job {
+    id: usize,
+    resource_path: String,
+}
+
+struct Decoded {
+    id: usize,
+    result: Result<ModelData, Error>,
+}
+
+struct Upload<'a> {
+    handle: AssetHandle<Model<'a>>,
+    program: &'a Program,
+    files: Vec<PathBuf>,
+    upload: ModelUpload,
+}
+
+/// Reads and decodes models on worker threads, then uploads them on the GL thread a few
+/// textures and meshes per frame, so large scenes stream in without stalling the window.
+pub struct BackgroundLoader<'a> {
+    gl: gl::Gl,
+    resources: &'a ResourceLoader,
+    defaults: &'a DefaultTextures,
+    jobs: Option<Sender<Job>>,
+    decoded: Receiver<Decoded>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: usize,
+    /// Models being read by the workers, by job id.
+    reading: HashMap<usize, (AssetHandle<Model<'a>>, &'a Program)>,
+    /// Models waiting for their upload, in the order they were decoded.
+    uploads: VecDeque<Upload<'a>>,
+}
+
+impl<'a> BackgroundLoader<'a> {
+    /// Starts one worker for every core besides the one running the GL thread.
+    pub fn new(
+        gl: &gl::Gl,
+        resources: &'a ResourceLoader,
+        defaults: &'a DefaultTextures,
+    ) -> BackgroundLoader<'a> {
+        let worker_count = thread::available_parallelism()
+            .map_or(1, |count| count.get().saturating_sub(1))
+            .max(1);
+
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (decoded_sender, decoded_receiver) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..worker_count)
+            .map(|_| {
+                let jobs = job_receiver.clone();
+                let decoded = decoded_sender.clone();
+                let reader = resources.reader().clone();
+
+                thread::spawn(move || loop {
+                    // The lock is released before reading, so the other workers can take the
+                    // next jobs meanwhile. Receiving fails once the loader is dropped.
+                    let job = jobs.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        break;
+                    };
+
+                    // A panicking reader fails the job instead of taking the worker down with it
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        reader.read_model(&job.resource_path)
+                    }))
+                    .unwrap_or_else(|payload| {
+                        let message = payload
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                            .or_else(|| payload.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+                        Err(Error::ReaderPanicked(message))
+                    });
+                    if decoded.send(Decoded { id: job.id, result }).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        BackgroundLoader {
+            gl: gl.clone(),
+            resources,
+            defaults,
+            jobs: Some(job_sender),
+            decoded: decoded_receiver,
+            workers,
+            next_id: 0,
+            reading: HashMap::new(),
+            uploads: VecDeque::new(),
+        }
+    }
+
+    /// Starts loading a model in any supported format. The handle holds an empty model until
+    /// the model is uploaded.
+    pub fn load_model(
+        &mut self,
+        resource_path: &str,
+        program: &'a Program,
+    ) -> Result<AssetHandle<Model<'a>>, Error> {
+        let handle = AssetHandle::new(Model::empty(&self.gl));
+        self.reload_model(&handle, resource_path, program)?;
+
+        Ok(handle)
+    }
+
+    /// Loads a model into an existing handle, which keeps its current model until the new
+    /// one is uploaded. Earlier loads into the handle that are still running are dropped, so
+    /// they cannot replace the newer model. Fails when the workers have stopped, leaving the
+    /// handle untouched.
+    pub fn reload_model(
+        &mut self,
+        handle: &AssetHandle<Model<'a>>,
+        resource_path: &str,
+        program: &'a Program,
+    ) -> Result<(), Error> {
+        let id = self.next_id;
+
+        let job = Job {
+            id,
+            resource_path: resource_path.to_string(),
+        };
+        let sent = self
+            .jobs
+            .as_ref()
+            .is_some_and(|jobs| jobs.send(job).is_ok());
+        if !sent {
+            return Err(Error::LoaderStopped);
+        }
+
+        self.next_id += 1;
+        handle.set_status(LoadStatus::Loading);
+
+        // Results of dropped jobs are ignored once the workers send them
+        self.reading
+            .retain(|_, (reading, _)| !reading.is_same(handle));
+        self.uploads.retain(|upload| !upload.handle.is_same(handle));
+        self.reading.insert(id, (handle.clone(), program));
+
+        Ok(())
+    }
+
+    /// Collects the models decoded by the workers and uploads textures and meshes until
+    /// `budget` bytes are uploaded. At least one texture or mesh is uploaded per call, so
+    /// assets larger than the budget still finish.
+    pub fn upload(&mut self, budget: usize) {
+        while let Ok(decoded) = self.decoded.try_recv() {
+            let Some((handle, program)) = self.reading.remove(&decoded.id) else {
+                continue;
+            };
+
+            match decoded.result {
+                Ok(data) => self.uploads.push_back(Upload {
+                    handle,
+                    program,
+                    files: data.files(),
+                    upload: data.upload(),
+                }),
+                Err(e) => handle.set_status(LoadStatus::Failed(e)),
+            }
+        }
+
+        let mut uploaded = 0;
+
+        while let Some(upload) = self.uploads.front_mut() {
+            match upload.upload.step(&self.gl, self.resources.cache()) {
+                Some(bytes) => uploaded += bytes,
+                None => {
+                    let Upload {
+                        handle,
+                        program,
+                        files,
+                        upload,
+                    } = self.uploads.pop_front().unwrap();
+
+                    *handle.get_mut() =
+                        upload.finish(&self.gl, self.resources.cache(), program, self.defaults);
+                    *handle.state.files.borrow_mut() = files;
+                    handle.set_status(LoadStatus::Ready);
+                }
+            }
+
+            if uploaded >= budget {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for BackgroundLoader<'_> {
+    fn drop(&mut self) {
+        // Closing the job channel stops the workers once they finish their current job
+        self.jobs = None;
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::mesh::Mesh;
+use crate::texture::{ColorSpace, SamplerDesc, Texture};
+
+use super::vfs;
+
+/// Identifies the source of an asset, so loading it again returns the already uploaded data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    /// A file, by its normalized path relative to the mounts.
+    Path(PathBuf),
+    /// Data embedded in another file, by the hash of its bytes.
+    Content(u64),
+}
+
+impl AssetKey {
+    pub fn path(path: &Path) -> AssetKey {
+        AssetKey::Path(vfs::normalize(path))
+    }
+
+    pub fn content(data: &[u8]) -> AssetKey {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        AssetKey::Content(hasher.finish())
+    }
+}
+
+/// Shares the textures and meshes of loaded assets between models. Entries stay cached
+/// while any model holds them, and until `evict_unused` is called after they are dropped.
+#[derive(Default)]
+pub struct AssetCache {
+    /// The same image sampled differently or in another color space is a separate texture.
+    textures: RefCell<HashMap<(AssetKey, ColorSpace, SamplerDesc), Rc<Texture>>>,
+    /// Meshes of a file by mesh and primitive index.
+    meshes: RefCell<HashMap<(AssetKey, usize, usize), Rc<Mesh>>>,
+}
+
+impl AssetCache {
+    pub fn new() -> AssetCache {
+        AssetCache::default()
+    }
+
+    /// Returns the cached texture, or creates and caches it with `create`.
+    pub fn texture<F: FnOnce() -> Texture>(
+        &self,
+        key: AssetKey,
+        color_space: ColorSpace,
+        sampler: &SamplerDesc,
+        create: F,
+    ) -> Rc<Texture> {
+        get_or_create(&self.textures, (key, color_space, *sampler), create)
+    }
+
+    /// Returns the cached mesh, or creates and caches it with `create`.
+    pub fn mesh<F: FnOnce() -> Mesh>(
+        &self,
+        key: AssetKey,
+        mesh_index: usize,
+        primitive_index: usize,
+        create: F,
+    ) -> Rc<Mesh> {
+        get_or_create(&self.meshes, (key, mesh_index, primitive_index), create)
+    }
+
+    /// Textures created from the asset, in any color space and with any sampler.
+    pub fn textures(&self, key: &AssetKey) -> Vec<Rc<Texture>> {
+        self.textures
+            .borrow()
+            .iter()
+            .filter(|((texture_key, _, _), _)| texture_key == key)
+            .map(|(_, texture)| texture.clone())
+            .collect()
+    }
+
+    /// Forgets the textures and meshes created from the asset, so loading it again reads
+    /// the changed file instead of returning them. Models keep using the forgotten ones.
+    pub fn invalidate(&self, key: &AssetKey) {
+        self.textures
+            .borrow_mut()
+            .retain(|(texture_key, _, _), _| texture_key != key);
+        self.meshes
+            .borrow_mut()
+            .retain(|(mesh_key, _, _), _| mesh_key != key);
+    }
+
+    /// Frees the textures and meshes no model uses anymore, returning how many were freed.
+    pub fn evict_unused(&self) -> usize {
+        evict(&self.textures) + evict(&self.meshes)
+    }
+}
+
+fn get_or_create<K: Eq + Hash, T, F: FnOnce() -> T>(
+    entries: &RefCell<HashMap<K, Rc<T>>>,
+    key: K,
+    create: F,
+) -> Rc<T> {
+    if let Some(asset) = entries.borrow().get(&key) {
+        return asset.clone();
+    }
+
+    // The map is not borrowed while creating, so `create` may use the cache itself
+    let asset = Rc::new(create());
+    entries.borrow_mut().insert(key, asset.clone());
+
+    asset
+}
+
+/// Drops the entries only the cache holds a reference to.
+fn evict<K, T>(entries: &RefCell<HashMap<K, Rc<T>>>) -> usize {
+    let mut entries = entries.borrow_mut();
+    let count = entries.len();
+
+    entries.retain(|_, asset| Rc::strong_count(asset) > 1);
+
+    count - entries.len()
+}
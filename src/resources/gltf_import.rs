@@ -0,0 +1,1195 @@
+use std::path::Path;
+
+use cgmath::{Matrix4, Point3, Quaternion, Rad, SquareMatrix, Vector2, Vector3, Vector4, Zero};
+
+use crate::animation::{Animation, Channel, Interpolation, Keyframes};
+use crate::camera::Projection;
+use crate::light::{Light, LightKind};
+use crate::material::{
+    AlphaMode, CullMode, MaterialFactors, MaterialTextures, TextureTransform, TextureTransforms,
+    UV_SETS,
+};
+use crate::mesh::{generate, MeshVertex, MorphTarget, SkinnedMeshVertex, Topology};
+use crate::model::{Node, Skin, MAX_JOINTS, MAX_MORPH_TARGETS};
+use crate::texture::{decode_image, ColorSpace, Filter, SamplerDesc, Wrap};
+
+use super::{
+    AssetKey, AssetReader, Error, ImageData, MaterialData, MeshData, ModelData, PrimitiveData,
+    Vertices,
+};
+
+/// Extensions that files may require, the others are optional and ignored.
+const SUPPORTED_EXTENSIONS: [&str; 2] = ["KHR_lights_punctual", "KHR_texture_transform"];
+
+impl AssetReader {
+    /// Reads a glTF or GLB file along with its buffers and images, and decodes everything
+    /// needed to upload it.
+    pub fn read_gltf(&self, resource_path: &str) -> Result<ModelData, Error> {
+        let path = Path::new(resource_path);
+        let current_directory = path.parent().unwrap_or(Path::new(""));
+
+        let gltf = gltf::Gltf::from_slice(&self.load_binary(path)?)?;
+
+        if let Some(extension) = gltf
+            .extensions_required()
+            .find(|extension| !SUPPORTED_EXTENSIONS.contains(extension))
+        {
+            return Err(Error::UnsupportedFeature(format!(
+                "required extension {}",
+                extension
+            )));
+        }
+
+        // Load buffers
+        let mut sources = vec![path.to_path_buf()];
+        let mut buffer_data = Vec::new();
+        for buffer in gltf.buffers() {
+            match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => {
+                    let (binary_data, _) = self.load_gltf_uri(current_directory, uri)?;
+                    buffer_data.push(binary_data);
+
+                    if !uri.starts_with("data:") {
+                        sources.push(current_directory.join(uri));
+                    }
+                }
+                gltf::buffer::Source::Bin => {
+                    let blob = gltf.blob.as_deref().ok_or(Error::MissingBinaryChunk)?;
+                    buffer_data.push(blob.into());
+                }
+            }
+        }
+
+        // Views are only validated against the declared buffer lengths, which files can get
+        // wrong, so they are checked against the loaded data before anything reads them
+        let view_data = |view: &gltf::buffer::View| {
+            let start = view.offset();
+            let end = start + view.length();
+
+            buffer_data[view.buffer().index()]
+                .get(start..end)
+                .ok_or_else(|| Error::InvalidData {
+                    path: path.to_path_buf(),
+                    message: format!(
+                        "buffer view {} runs past the end of buffer {}",
+                        view.index(),
+                        view.buffer().index()
+                    ),
+                })
+        };
+        for view in gltf.views() {
+            view_data(&view)?;
+        }
+
+        // Load materials
+        let mut materials = Vec::new();
+        let mut images: Vec<ImageData> = Vec::new();
+
+        // Embedded images are keyed by their content and files by their path, so textures
+        // referenced by several materials are only decoded once and the asset cache can share
+        // them with other models
+        let mut load_texture = |texture: &gltf::Texture, color_space: ColorSpace| {
+            let sampler = sampler_desc(&texture.sampler());
+
+            let (key, data, mime_type) = match texture.source().source() {
+                gltf::image::Source::View { view, mime_type } => {
+                    let data = view_data(&view)?.to_vec();
+
+                    (AssetKey::content(&data), data, Some(mime_type))
+                }
+                gltf::image::Source::Uri { uri, mime_type } if uri.starts_with("data:") => {
+                    let (data, uri_mime_type) = self.load_gltf_uri(current_directory, uri)?;
+
+                    (AssetKey::content(&data), data, uri_mime_type.or(mime_type))
+                }
+                gltf::image::Source::Uri { uri, mime_type: _ } => {
+                    let path = current_directory.join(uri);
+
+                    (AssetKey::path(&path), self.load_binary(&path)?, None)
+                }
+            };
+
+            if let Some(index) = images.iter().position(|image| {
+                image.key == key && image.color_space == color_space && image.sampler == sampler
+            }) {
+                return Ok(index);
+            }
+
+            images.push(ImageData {
+                key,
+                image: decode_image(&data, mime_type).map_err(Error::Texture)?,
+                sampler,
+                color_space,
+            });
+
+            Ok::<_, Error>(images.len() - 1)
+        };
+
+        // Color textures are authored in sRGB while data textures are linear
+        let mut load_texture_or = |texture: Option<gltf::Texture>, color_space: ColorSpace| {
+            texture
+                .map(|texture| load_texture(&texture, color_space))
+                .transpose()
+        };
+
+        let mut load_material = |material: gltf::Material| -> Result<MaterialData, Error> {
+            let pbr = material.pbr_metallic_roughness();
+
+            let normal = material.normal_texture();
+            let normal_scale = normal.as_ref().map_or(1.0, |info| info.scale());
+
+            let occlusion = material.occlusion_texture();
+            let occlusion_strength = occlusion.as_ref().map_or(1.0, |info| info.strength());
+
+            let transforms = TextureTransforms {
+                base_color: pbr
+                    .base_color_texture()
+                    .map_or(Ok(TextureTransform::default()), |info| {
+                        texture_transform(&info)
+                    })?,
+                metallic_roughness: pbr
+                    .metallic_roughness_texture()
+                    .map_or(Ok(TextureTransform::default()), |info| {
+                        texture_transform(&info)
+                    })?,
+                normal: normal
+                    .as_ref()
+                    .map_or(Ok(TextureTransform::default()), |info| {
+                        json_texture_transform(
+                            info.tex_coord(),
+                            info.extension_value("KHR_texture_transform"),
+                        )
+                    })?,
+                occlusion: occlusion
+                    .as_ref()
+                    .map_or(Ok(TextureTransform::default()), |info| {
+                        json_texture_transform(
+                            info.tex_coord(),
+                            info.extension_value("KHR_texture_transform"),
+                        )
+                    })?,
+                emissive: material
+                    .emissive_texture()
+                    .map_or(Ok(TextureTransform::default()), |info| {
+                        texture_transform(&info)
+                    })?,
+            };
+
+            let textures = MaterialTextures {
+                base_color: load_texture_or(
+                    pbr.base_color_texture().map(|info| info.texture()),
+                    ColorSpace::Srgb,
+                )?,
+                metallic_roughness: load_texture_or(
+                    pbr.metallic_roughness_texture().map(|info| info.texture()),
+                    ColorSpace::Linear,
+                )?,
+                normal: load_texture_or(normal.map(|info| info.texture()), ColorSpace::Linear)?,
+                occlusion: load_texture_or(
+                    occlusion.map(|info| info.texture()),
+                    ColorSpace::Linear,
+                )?,
+                emissive: load_texture_or(
+                    material.emissive_texture().map(|info| info.texture()),
+                    ColorSpace::Srgb,
+                )?,
+                transforms,
+            };
+
+            let factors = MaterialFactors {
+                base_color: pbr.base_color_factor().into(),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                normal_scale,
+                occlusion_strength,
+                emissive: material.emissive_factor().into(),
+            };
+
+            let alpha_mode = match material.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask {
+                    cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                },
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+            };
+
+            let cull_mode = match material.double_sided() {
+                true => CullMode::Disabled,
+                false => CullMode::Back,
+            };
+
+            Ok(MaterialData {
+                textures,
+                factors,
+                alpha_mode,
+                cull_mode,
+            })
+        };
+
+        for material in gltf.materials() {
+            let material = load_material(material)?;
+            materials.push(material);
+        }
+
+        // Primitives without a material use the glTF default material, added after the others
+        let default_material_index = materials.len();
+        if let Some(primitive) = gltf
+            .meshes()
+            .flat_map(|mesh| mesh.primitives())
+            .find(|primitive| primitive.material().index().is_none())
+        {
+            materials.push(load_material(primitive.material())?);
+        }
+
+        let key = AssetKey::path(path);
+        let mut meshes = Vec::new();
+
+        for mesh in gltf.meshes() {
+            let primitives = mesh
+                .primitives()
+                .map(|primitive| {
+                    Ok(PrimitiveData {
+                        mesh: load_mesh(&key, &mesh, &primitive, &buffer_data)?,
+                        material_index: primitive
+                            .material()
+                            .index()
+                            .unwrap_or(default_material_index),
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            meshes.push(primitives);
+        }
+
+        // Load skins
+        let skins = gltf
+            .skins()
+            .map(|skin| {
+                let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+
+                if joints.len() > MAX_JOINTS {
+                    return Err(Error::UnsupportedFeature(format!(
+                        "skin with {} joints, at most {} are supported",
+                        joints.len(),
+                        MAX_JOINTS
+                    )));
+                }
+
+                // Missing inverse bind matrices default to identity matrices
+                let reader = skin.reader(|buffer| Some(&buffer_data[buffer.index()]));
+                let inverse_bind_matrices: Vec<_> = match reader.read_inverse_bind_matrices() {
+                    Some(matrices) => matrices.map(Matrix4::from).collect(),
+                    None => vec![Matrix4::identity(); joints.len()],
+                };
+
+                // Joints without a matrix would be left out of the joint matrices
+                if inverse_bind_matrices.len() != joints.len() {
+                    return Err(Error::InvalidData {
+                        path: path.to_path_buf(),
+                        message: format!(
+                            "skin {} has {} inverse bind matrices for {} joints",
+                            skin.index(),
+                            inverse_bind_matrices.len(),
+                            joints.len()
+                        ),
+                    });
+                }
+
+                Ok(Skin::new(joints, inverse_bind_matrices))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Load node hierarchy
+        let nodes = gltf
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                let [x, y, z, w] = rotation;
+
+                let weights = node
+                    .weights()
+                    .or_else(|| node.mesh().and_then(|mesh| mesh.weights()))
+                    .map(|weights| weights.to_vec())
+                    .unwrap_or_default();
+
+                Node {
+                    camera: node.camera().map(|camera| load_projection(&camera)),
+                    light: node.light().map(|light| load_light(&light)),
+                    ..Node::new(
+                        node.mesh().map(|mesh| mesh.index()),
+                        node.skin().map(|skin| skin.index()),
+                        node.children().map(|child| child.index()).collect(),
+                        translation.into(),
+                        Quaternion::new(w, x, y, z),
+                        scale.into(),
+                        weights,
+                    )
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let animations = gltf
+            .animations()
+            .map(|animation| load_animation(&animation, &buffer_data))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let roots: Vec<usize> = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+            Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+            // Without scenes, every node that is not a child of another node is a root
+            None => (0..nodes.len())
+                .filter(|index| !nodes.iter().any(|node| node.children.contains(index)))
+                .collect(),
+        };
+
+        check_hierarchy(&nodes, &roots).map_err(|message| Error::InvalidData {
+            path: path.to_path_buf(),
+            message,
+        })?;
+
+        // Joints past the end of the skin would read the matrices of no joint
+        for node in &nodes {
+            let (Some(mesh), Some(skin)) = (node.mesh, node.skin) else {
+                continue;
+            };
+
+            let joint_count = skins[skin].joints.len();
+            let out_of_range = meshes[mesh]
+                .iter()
+                .filter_map(|primitive| match &primitive.mesh.vertices {
+                    Vertices::Skinned(vertices) => Some(vertices),
+                    Vertices::Static(_) => None,
+                })
+                .flatten()
+                .flat_map(|vertex| vertex.joints())
+                .find(|joint| *joint as usize >= joint_count);
+
+            if let Some(joint) = out_of_range {
+                return Err(Error::InvalidData {
+                    path: path.to_path_buf(),
+                    message: format!(
+                        "mesh {} uses joint {} of skin {}, which has {} joints",
+                        mesh, joint, skin, joint_count
+                    ),
+                });
+            }
+        }
+
+        Ok(ModelData {
+            sources,
+            nodes,
+            roots,
+            meshes,
+            images,
+            materials,
+            skins,
+            animations,
+        })
+    }
+
+    /// Loads the data referenced by a glTF uri, which is either a path relative to
+    /// `directory` or an embedded `data:` uri. Data uris also return their mime type.
+    fn load_gltf_uri<'u>(
+        &self,
+        directory: &Path,
+        uri: &'u str,
+    ) -> Result<(Vec<u8>, Option<&'u str>), Error> {
+        match uri.strip_prefix("data:") {
+            Some(data_uri) => {
+                let (header, data) = data_uri.split_once(',').ok_or(Error::InvalidDataUri)?;
+
+                let (mime_type, data) = match header.strip_suffix(";base64") {
+                    Some(mime_type) => (mime_type, base64::decode(data)?),
+                    None => (header, data.as_bytes().to_vec()),
+                };
+
+                let mime_type = match mime_type {
+                    "" => None,
+                    mime_type => Some(mime_type),
+                };
+
+                Ok((data, mime_type))
+            }
+            None => Ok((self.load_binary(&directory.join(uri))?, None)),
+        }
+    }
+}
+
+/// Reads the UV set of a texture along with its KHR_texture_transform, which may override it.
+/// Checks that the nodes form trees, which the walks over the hierarchy rely on to end. glTF
+/// validation lets nodes with several parents and cycles through.
+fn check_hierarchy(nodes: &[Node], roots: &[usize]) -> Result<(), String> {
+    let mut parents = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for child in &node.children {
+            if let Some(parent) = parents[*child].replace(index) {
+                return Err(format!(
+                    "node {} is a child of both node {} and node {}",
+                    child, parent, index
+                ));
+            }
+        }
+    }
+
+    if let Some(root) = roots.iter().find(|root| parents[**root].is_some()) {
+        return Err(format!("scene root {} is a child of another node", root));
+    }
+
+    // With a single parent per node, the nodes that cannot be reached from the nodes without
+    // a parent lie on a cycle or below one
+    let mut reached = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = (0..nodes.len())
+        .filter(|index| parents[*index].is_none())
+        .collect();
+    while let Some(index) = stack.pop() {
+        reached[index] = true;
+        stack.extend(&nodes[index].children);
+    }
+
+    match reached.iter().position(|reached| !reached) {
+        Some(index) => Err(format!("node {} is part of a cycle", index)),
+        None => Ok(()),
+    }
+}
+
+fn texture_transform(info: &gltf::texture::Info) -> Result<TextureTransform, Error> {
+    let transform = match info.texture_transform() {
+        Some(transform) => TextureTransform {
+            uv_set: transform.tex_coord().unwrap_or(info.tex_coord()),
+            offset: transform.offset().into(),
+            rotation: transform.rotation(),
+            scale: transform.scale().into(),
+        },
+        None => TextureTransform {
+            uv_set: info.tex_coord(),
+            ..TextureTransform::default()
+        },
+    };
+
+    check_uv_set(transform)
+}
+
+/// Normal and occlusion textures only expose KHR_texture_transform as raw JSON.
+fn json_texture_transform(
+    tex_coord: u32,
+    extension: Option<&serde_json::Value>,
+) -> Result<TextureTransform, Error> {
+    use gltf::json::extensions::texture::TextureTransform as Json;
+
+    let transform = match extension {
+        Some(extension) => serde_json::from_value::<Json>(extension.clone())
+            .map_err(|e| Error::GltfParse(gltf::Error::Deserialize(e)))?,
+        None => Json::default(),
+    };
+
+    check_uv_set(TextureTransform {
+        uv_set: transform.tex_coord.unwrap_or(tex_coord),
+        offset: transform.offset.0.into(),
+        rotation: transform.rotation.0,
+        scale: transform.scale.0.into(),
+    })
+}
+
+fn check_uv_set(transform: TextureTransform) -> Result<TextureTransform, Error> {
+    match transform.uv_set < UV_SETS {
+        true => Ok(transform),
+        false => Err(Error::UnsupportedFeature(format!(
+            "texture coordinate set {}, at most {} are supported",
+            transform.uv_set, UV_SETS
+        ))),
+    }
+}
+
+fn sampler_desc(sampler: &gltf::texture::Sampler) -> SamplerDesc {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let wrap = |mode| match mode {
+        WrappingMode::Repeat => Wrap::Repeat,
+        WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+        WrappingMode::ClampToEdge => Wrap::ClampToEdge,
+    };
+
+    // Filters left undefined by the asset keep the renderer defaults
+    let default = SamplerDesc::default();
+
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => (Filter::Nearest, None),
+        Some(MinFilter::Linear) => (Filter::Linear, None),
+        Some(MinFilter::NearestMipmapNearest) => (Filter::Nearest, Some(Filter::Nearest)),
+        Some(MinFilter::LinearMipmapNearest) => (Filter::Linear, Some(Filter::Nearest)),
+        Some(MinFilter::NearestMipmapLinear) => (Filter::Nearest, Some(Filter::Linear)),
+        Some(MinFilter::LinearMipmapLinear) => (Filter::Linear, Some(Filter::Linear)),
+        None => (default.min_filter, default.mipmap_filter),
+    };
+
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => Filter::Nearest,
+        Some(MagFilter::Linear) => Filter::Linear,
+        None => default.mag_filter,
+    };
+
+    SamplerDesc {
+        wrap_s: wrap(sampler.wrap_s()),
+        wrap_t: wrap(sampler.wrap_t()),
+        min_filter,
+        mag_filter,
+        mipmap_filter,
+        ..default
+    }
+}
+
+fn load_projection(camera: &gltf::Camera) -> Projection {
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => Projection::Perspective {
+            yfov: Rad(perspective.yfov()),
+            aspect: perspective.aspect_ratio(),
+            near: perspective.znear(),
+            far: perspective.zfar(),
+        },
+        gltf::camera::Projection::Orthographic(orthographic) => Projection::Orthographic {
+            xmag: orthographic.xmag(),
+            ymag: orthographic.ymag(),
+            near: orthographic.znear(),
+            far: orthographic.zfar(),
+        },
+    }
+}
+
+fn load_light(light: &gltf::khr_lights_punctual::Light) -> Light {
+    use gltf::khr_lights_punctual::Kind;
+
+    let kind = match light.kind() {
+        Kind::Directional => LightKind::Directional,
+        Kind::Point => LightKind::Point,
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => LightKind::Spot {
+            inner_cone_angle: Rad(inner_cone_angle),
+            outer_cone_angle: Rad(outer_cone_angle),
+        },
+    };
+
+    Light::new(kind, light.color().into(), light.intensity(), light.range())
+}
+
+fn load_animation(
+    animation: &gltf::Animation,
+    buffer_data: &[Vec<u8>],
+) -> Result<Animation, Error> {
+    use gltf::animation::util::ReadOutputs;
+
+    let channels = animation
+        .channels()
+        .enumerate()
+        .map(|(index, channel)| {
+            let missing_data = || Error::MissingAnimationData {
+                animation: animation.index(),
+                channel: index,
+            };
+
+            let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()]));
+
+            let interpolation = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+            };
+
+            let times: Vec<f32> = reader.read_inputs().ok_or_else(missing_data)?.collect();
+
+            let keyframes = match reader.read_outputs().ok_or_else(missing_data)? {
+                ReadOutputs::Translations(translations) => {
+                    Keyframes::Translation(translations.map(Vector3::from).collect())
+                }
+                ReadOutputs::Rotations(rotations) => Keyframes::Rotation(
+                    rotations
+                        .into_f32()
+                        .map(|[x, y, z, w]| Quaternion::new(w, x, y, z))
+                        .collect(),
+                ),
+                ReadOutputs::Scales(scales) => {
+                    Keyframes::Scale(scales.map(Vector3::from).collect())
+                }
+                ReadOutputs::MorphTargetWeights(weights) => {
+                    // Weights of all morph targets are stored one after the other for every
+                    // output value
+                    let weights: Vec<f32> = weights.into_f32().collect();
+                    let values_per_keyframe = match interpolation {
+                        Interpolation::CubicSpline => 3,
+                        _ => 1,
+                    };
+                    let targets = weights.len() / (times.len() * values_per_keyframe).max(1);
+
+                    Keyframes::MorphWeights(
+                        weights
+                            .chunks(targets.max(1))
+                            .map(|chunk| chunk.to_vec())
+                            .collect(),
+                    )
+                }
+            };
+
+            let expected_values = match interpolation {
+                Interpolation::CubicSpline => times.len() * 3,
+                _ => times.len(),
+            };
+            if times.is_empty() || keyframes.len() < expected_values {
+                return Err(missing_data());
+            }
+
+            Ok(Channel::new(
+                channel.target().node().index(),
+                interpolation,
+                times,
+                keyframes,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(Animation::new(channels))
+}
+
+fn load_mesh(
+    key: &AssetKey,
+    mesh: &gltf::Mesh,
+    primitive: &gltf::Primitive,
+    buffer_data: &[Vec<u8>],
+) -> Result<MeshData, Error> {
+    let missing_attribute = |attribute| Error::MissingAttribute {
+        mesh: mesh.index(),
+        primitive: primitive.index(),
+        attribute,
+    };
+    let invalid_accessor = |attribute, message| Error::InvalidAccessor {
+        mesh: mesh.index(),
+        primitive: primitive.index(),
+        attribute,
+        message,
+    };
+
+    if primitive.mode() != gltf::mesh::Mode::Triangles {
+        return Err(Error::UnsupportedFeature(format!(
+            "primitive mode {:?}",
+            primitive.mode()
+        )));
+    }
+
+    let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+
+    // Read vertex attributes
+    let mut positions: Vec<Point3<f32>> = reader
+        .read_positions()
+        .ok_or_else(|| missing_attribute("POSITION"))?
+        .map(Point3::from)
+        .collect();
+    // Untextured primitives often have no UVs, the default textures they sample are 1x1
+    let mut uvs: Vec<Vector2<f32>> = match reader.read_tex_coords(0) {
+        Some(uvs) => uvs.into_f32().map(Vector2::from).collect(),
+        None => vec![Vector2::zero(); positions.len()],
+    };
+    // Vertices without a second UV set or colors use the first set and white
+    let mut uvs1: Vec<Vector2<f32>> = match reader.read_tex_coords(1) {
+        Some(uvs1) => uvs1.into_f32().map(Vector2::from).collect(),
+        None => uvs.clone(),
+    };
+    let mut colors: Vec<Vector4<f32>> = match reader.read_colors(0) {
+        Some(colors) => colors.into_rgba_f32().map(Vector4::from).collect(),
+        None => vec![Vector4::new(1.0, 1.0, 1.0, 1.0); positions.len()],
+    };
+    let normals: Option<Vec<Vector3<f32>>> = reader
+        .read_normals()
+        .map(|normals| normals.map(Vector3::from).collect());
+    let tangents: Option<Vec<Vector4<f32>>> = reader
+        .read_tangents()
+        .map(|tangents| tangents.map(Vector4::from).collect());
+
+    // Read skinning attributes, only the first set of four influences is supported
+    let mut joints: Option<Vec<[u32; 4]>> = reader.read_joints(0).map(|joints| {
+        joints
+            .into_u16()
+            .map(|joints| joints.map(u32::from))
+            .collect()
+    });
+    let mut weights: Option<Vec<Vector4<f32>>> = reader
+        .read_weights(0)
+        .map(|weights| weights.into_f32().map(Vector4::from).collect());
+
+    // Read morph target displacements, attributes a target leaves out are not displaced
+    let vertex_count = positions.len();
+    let mut morph_targets: Vec<MorphTarget> = reader
+        .read_morph_targets()
+        .map(|(positions, normals, tangents)| {
+            let [positions, normals, tangents] =
+                [positions, normals, tangents].map(|deltas| match deltas {
+                    Some(deltas) => deltas.map(Vector3::from).collect(),
+                    None => vec![Vector3::zero(); vertex_count],
+                });
+
+            MorphTarget {
+                positions,
+                normals,
+                tangents,
+            }
+        })
+        .collect();
+
+    if morph_targets.len() > MAX_MORPH_TARGETS {
+        return Err(Error::UnsupportedFeature(format!(
+            "primitive with {} morph targets, at most {} are supported",
+            morph_targets.len(),
+            MAX_MORPH_TARGETS
+        )));
+    }
+
+    // Read vertex indices, primitives without them draw their vertices in order
+    let mut indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertex_count as u32).collect(),
+    };
+
+    // Every attribute needs one value per vertex and every index an existing vertex, malformed
+    // files would otherwise panic or shift the attributes of the following vertices
+    let check_count = |attribute, count: usize| match count == vertex_count {
+        true => Ok(()),
+        false => Err(invalid_accessor(
+            attribute,
+            format!("{} values for {} vertices", count, vertex_count),
+        )),
+    };
+
+    check_count("TEXCOORD_0", uvs.len())?;
+    check_count("TEXCOORD_1", uvs1.len())?;
+    check_count("COLOR_0", colors.len())?;
+    if let Some(normals) = &normals {
+        check_count("NORMAL", normals.len())?;
+    }
+    if let Some(tangents) = &tangents {
+        check_count("TANGENT", tangents.len())?;
+    }
+    if let Some(joints) = &joints {
+        check_count("JOINTS_0", joints.len())?;
+
+        // The joint matrices are uploaded for at most MAX_JOINTS joints
+        if let Some(joint) = joints
+            .iter()
+            .flatten()
+            .find(|joint| **joint as usize >= MAX_JOINTS)
+        {
+            return Err(invalid_accessor(
+                "JOINTS_0",
+                format!(
+                    "joint {} is out of range, at most {} joints are supported",
+                    joint, MAX_JOINTS
+                ),
+            ));
+        }
+    }
+    if let Some(weights) = &weights {
+        check_count("WEIGHTS_0", weights.len())?;
+    }
+    for target in &morph_targets {
+        check_count("morph target POSITION", target.positions.len())?;
+        check_count("morph target NORMAL", target.normals.len())?;
+        check_count("morph target TANGENT", target.tangents.len())?;
+    }
+
+    if let Some(index) = indices
+        .iter()
+        .find(|index| **index as usize >= vertex_count)
+    {
+        return Err(invalid_accessor(
+            "indices",
+            format!(
+                "index {} is out of range for {} vertices",
+                index, vertex_count
+            ),
+        ));
+    }
+    if !indices.len().is_multiple_of(3) {
+        return Err(invalid_accessor(
+            "indices",
+            format!("{} indices do not form whole triangles", indices.len()),
+        ));
+    }
+
+    // Generate flat normals when missing, which requires every triangle to have
+    // its own vertices. Any provided tangents are meaningless without normals.
+    let (normals, tangents) = match normals {
+        Some(normals) => (normals, tangents),
+        None => {
+            positions = generate::unweld(&positions, &indices);
+            uvs = generate::unweld(&uvs, &indices);
+            uvs1 = generate::unweld(&uvs1, &indices);
+            colors = generate::unweld(&colors, &indices);
+            joints = joints.map(|joints| generate::unweld(&joints, &indices));
+            weights = weights.map(|weights| generate::unweld(&weights, &indices));
+            // Generated flat normals ignore the normal and tangent displacements
+            morph_targets = morph_targets
+                .into_iter()
+                .map(|target| MorphTarget {
+                    positions: generate::unweld(&target.positions, &indices),
+                    normals: vec![Vector3::zero(); indices.len()],
+                    tangents: vec![Vector3::zero(); indices.len()],
+                })
+                .collect();
+            indices = (0..indices.len() as u32).collect();
+
+            (generate::flat_normals(&positions), None)
+        }
+    };
+
+    let tangents =
+        tangents.unwrap_or_else(|| generate::tangents(&positions, &normals, &uvs, &indices));
+
+    let vertices = positions
+        .into_iter()
+        .zip(uvs)
+        .zip(normals)
+        .zip(tangents)
+        .zip(uvs1)
+        .zip(colors)
+        .map(|(((((position, uv), normal), tangent), uv1), color)| {
+            let bitangent = generate::bitangent(normal, tangent);
+
+            MeshVertex::new(position, uv, normal, tangent.truncate(), bitangent)
+                .with_uv1(uv1)
+                .with_color(color)
+        });
+
+    let indices = indices.into_iter().map(|index| index as i32).collect();
+
+    let vertices = match (joints, weights) {
+        (Some(joints), Some(weights)) => Vertices::Skinned(
+            vertices
+                .zip(joints)
+                .zip(weights)
+                .map(|((vertex, joints), weights)| SkinnedMeshVertex::new(vertex, joints, weights))
+                .collect(),
+        ),
+        _ => Vertices::Static(vertices.collect()),
+    };
+
+    Ok(MeshData {
+        key: key.clone(),
+        mesh_index: mesh.index(),
+        primitive_index: primitive.index(),
+        vertices,
+        indices,
+        topology: Topology::Triangles,
+        morph_targets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::resources::MemoryMount;
+
+    /// Builds a glTF document with a single triangle primitive with the given attributes,
+    /// along with the buffer it refers to as `triangle.bin`.
+    fn triangle(
+        positions: &[[f32; 3]],
+        uvs: Option<&[[f32; 2]]>,
+        indices: Option<&[u32]>,
+    ) -> (serde_json::Value, Vec<u8>) {
+        let mut bin = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut attributes = serde_json::Map::new();
+
+        let mut push_accessor = |bin: &mut Vec<u8>, data: Vec<u8>, accessor: serde_json::Value| {
+            buffer_views.push(json!({
+                "buffer": 0,
+                "byteOffset": bin.len(),
+                "byteLength": data.len(),
+            }));
+            bin.extend(data);
+
+            let mut accessor = accessor;
+            accessor["bufferView"] = json!(buffer_views.len() - 1);
+            accessors.push(accessor);
+            accessors.len() - 1
+        };
+
+        let position_data = positions.iter().flatten().flat_map(|v| v.to_le_bytes());
+        let position = push_accessor(
+            &mut bin,
+            position_data.collect(),
+            json!({
+                "componentType": 5126,
+                "count": positions.len(),
+                "type": "VEC3",
+                "min": [-1.0, -1.0, -1.0],
+                "max": [1.0, 1.0, 1.0],
+            }),
+        );
+        attributes.insert("POSITION".to_string(), json!(position));
+
+        if let Some(uvs) = uvs {
+            let uv_data = uvs.iter().flatten().flat_map(|v| v.to_le_bytes());
+            let uv = push_accessor(
+                &mut bin,
+                uv_data.collect(),
+                json!({ "componentType": 5126, "count": uvs.len(), "type": "VEC2" }),
+            );
+            attributes.insert("TEXCOORD_0".to_string(), json!(uv));
+        }
+
+        let mut primitive = json!({ "attributes": attributes });
+        if let Some(indices) = indices {
+            let index_data = indices.iter().flat_map(|index| index.to_le_bytes());
+            let index = push_accessor(
+                &mut bin,
+                index_data.collect(),
+                json!({ "componentType": 5125, "count": indices.len(), "type": "SCALAR" }),
+            );
+            primitive["indices"] = json!(index);
+        }
+
+        let document = json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [primitive] }],
+            "accessors": accessors,
+            "bufferViews": buffer_views,
+            "buffers": [{ "uri": "triangle.bin", "byteLength": bin.len() }],
+        });
+
+        (document, bin)
+    }
+
+    /// Reads a glTF document and its buffer from memory.
+    fn read_document(document: serde_json::Value, bin: Vec<u8>) -> Result<ModelData, Error> {
+        let reader = AssetReader::new().with_mount(
+            MemoryMount::new()
+                .with_file("triangle.gltf", document.to_string())
+                .with_file("triangle.bin", bin),
+        );
+        reader.read_gltf("triangle.gltf")
+    }
+
+    fn read_triangle(
+        positions: &[[f32; 3]],
+        uvs: Option<&[[f32; 2]]>,
+        indices: Option<&[u32]>,
+    ) -> Result<ModelData, Error> {
+        let (document, bin) = triangle(positions, uvs, indices);
+        read_document(document, bin)
+    }
+
+    const POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    const UVS: [[f32; 2]; 3] = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+
+    #[test]
+    fn reads_indexed_triangle() {
+        let model = read_triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 2])).unwrap();
+
+        let mesh = &model.meshes[0][0].mesh;
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert!(matches!(&mesh.vertices, Vertices::Static(vertices) if vertices.len() == 3));
+    }
+
+    #[test]
+    fn reads_non_indexed_triangle() {
+        let model = read_triangle(&POSITIONS, Some(&UVS), None).unwrap();
+
+        assert_eq!(model.meshes[0][0].mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn reads_triangle_without_uvs() {
+        let model = read_triangle(&POSITIONS, None, Some(&[0, 1, 2])).unwrap();
+
+        assert!(matches!(
+            &model.meshes[0][0].mesh.vertices,
+            Vertices::Static(vertices) if vertices.len() == 3
+        ));
+    }
+
+    #[test]
+    fn rejects_views_past_the_end_of_the_buffer() {
+        let (document, mut bin) = triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 2]));
+        bin.truncate(bin.len() - 4);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    /// Builds the triangle skinned to a single joint of a skin with `joint_count` joints and
+    /// `matrix_count` inverse bind matrices.
+    fn skinned_triangle(
+        joint: u16,
+        joint_count: usize,
+        matrix_count: usize,
+    ) -> (serde_json::Value, Vec<u8>) {
+        let (mut document, mut bin) = triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 2]));
+
+        let mut push_accessor = |data: Vec<u8>, mut accessor: serde_json::Value| {
+            let views = document["bufferViews"].as_array_mut().unwrap();
+            views.push(json!({
+                "buffer": 0,
+                "byteOffset": bin.len(),
+                "byteLength": data.len(),
+            }));
+            accessor["bufferView"] = json!(views.len() - 1);
+            bin.extend(data);
+
+            let accessors = document["accessors"].as_array_mut().unwrap();
+            accessors.push(accessor);
+            accessors.len() - 1
+        };
+
+        let joint_data = [joint, 0, 0, 0].repeat(3);
+        let joints = push_accessor(
+            joint_data
+                .iter()
+                .flat_map(|joint| joint.to_le_bytes())
+                .collect(),
+            json!({ "componentType": 5123, "count": 3, "type": "VEC4" }),
+        );
+        let weight_data = [1.0f32, 0.0, 0.0, 0.0].repeat(3);
+        let weights = push_accessor(
+            weight_data
+                .iter()
+                .flat_map(|weight| weight.to_le_bytes())
+                .collect(),
+            json!({ "componentType": 5126, "count": 3, "type": "VEC4" }),
+        );
+        let matrix: [f32; 16] = *Matrix4::identity().as_ref();
+        let matrices = push_accessor(
+            matrix
+                .repeat(matrix_count)
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            json!({ "componentType": 5126, "count": matrix_count, "type": "MAT4" }),
+        );
+
+        let attributes = &mut document["meshes"][0]["primitives"][0]["attributes"];
+        attributes["JOINTS_0"] = json!(joints);
+        attributes["WEIGHTS_0"] = json!(weights);
+
+        let joint_nodes: Vec<usize> = (1..=joint_count).collect();
+        let mut nodes = vec![json!({ "mesh": 0, "skin": 0, "children": joint_nodes })];
+        nodes.extend(joint_nodes.iter().map(|_| json!({})));
+        document["nodes"] = json!(nodes);
+        document["skins"] = json!([{ "joints": joint_nodes, "inverseBindMatrices": matrices }]);
+        document["buffers"][0]["byteLength"] = json!(bin.len());
+
+        (document, bin)
+    }
+
+    #[test]
+    fn reads_skinned_triangle() {
+        let (document, bin) = skinned_triangle(1, 2, 2);
+        let model = read_document(document, bin).unwrap();
+
+        assert_eq!(model.skins[0].joints, vec![1, 2]);
+        assert!(matches!(
+            &model.meshes[0][0].mesh.vertices,
+            Vertices::Skinned(vertices) if vertices.iter().all(|v| v.joints() == [1, 0, 0, 0])
+        ));
+    }
+
+    #[test]
+    fn rejects_skins_missing_inverse_bind_matrices() {
+        let (document, bin) = skinned_triangle(0, 2, 1);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_joints_outside_the_skin() {
+        let (document, bin) = skinned_triangle(2, 2, 2);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_joints_past_the_supported_count() {
+        let (document, bin) = skinned_triangle(MAX_JOINTS as u16, 2, 2);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidAccessor {
+                attribute: "JOINTS_0",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_nodes_with_two_parents() {
+        let (mut document, bin) = triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 2]));
+        document["nodes"] = json!([{ "children": [2] }, { "children": [2] }, { "mesh": 0 }]);
+        document["scenes"] = json!([{ "nodes": [0, 1] }]);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_node_cycles() {
+        let (mut document, bin) = triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 2]));
+        document["nodes"] = json!([{ "mesh": 0 }, { "children": [2] }, { "children": [1] }]);
+
+        assert!(matches!(
+            read_document(document, bin),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_range_index() {
+        let result = read_triangle(&POSITIONS, Some(&UVS), Some(&[0, 1, 3]));
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidAccessor {
+                attribute: "indices",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_short_attribute() {
+        let result = read_triangle(&POSITIONS, Some(&UVS[..2]), Some(&[0, 1, 2]));
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidAccessor {
+                attribute: "TEXCOORD_0",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_long_attribute() {
+        let uvs = [UVS[0], UVS[1], UVS[2], UVS[0]];
+        let result = read_triangle(&POSITIONS, Some(&uvs), Some(&[0, 1, 2]));
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidAccessor {
+                attribute: "TEXCOORD_0",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+mod background;
+mod cache;
+mod gltf_import;
+mod model_data;
+mod obj_import;
+mod ply_import;
+mod scan_import;
+mod stl_import;
+mod vfs;
+mod watcher;
+
+use std::env;
+use std::ffi;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use image::ImageError;
+use image::{ImageBuffer, Rgb32FImage};
+
+use crate::texture::TextureImage;
+
+pub use self::background::{BackgroundLoader, LoadStatus};
+pub use self::cache::{AssetCache, AssetKey};
+pub use self::model_data::{
+    ImageData, MaterialData, MeshData, ModelData, ModelUpload, PrimitiveData, Vertices,
+};
+pub use self::vfs::{DirectoryMount, MemoryMount, Mount, PackMount};
+pub use self::watcher::AssetWatcher;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    FileContainsNil(std::ffi::NulError),
+    FailedToGetExePath,
+    NotFound {
+        path: PathBuf,
+        searched: Vec<String>,
+    },
+    FailedToDecodeImage(ImageError),
+    InvalidDataUri,
+    FailedToDecodeBase64(base64::DecodeError),
+    GltfParse(gltf::Error),
+    MissingBinaryChunk,
+    MissingAttribute {
+        mesh: usize,
+        primitive: usize,
+        attribute: &'static str,
+    },
+    InvalidAccessor {
+        mesh: usize,
+        primitive: usize,
+        attribute: &'static str,
+        message: String,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    InvalidData {
+        path: PathBuf,
+        message: String,
+    },
+    MissingAnimationData {
+        animation: usize,
+        channel: usize,
+    },
+    UnsupportedFeature(String),
+    Texture(String),
+    LoaderStopped,
+    ReaderPanicked(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::FileContainsNil(e) => write!(f, "File contains a nil byte: {}", e),
+            Error::FailedToGetExePath => write!(f, "Failed to get the executable path"),
+            Error::NotFound { path, searched } => write!(
+                f,
+                "Could not find {}, searched in {}",
+                path.display(),
+                searched.join(", ")
+            ),
+            Error::FailedToDecodeImage(e) => write!(f, "Failed to decode image: {}", e),
+            Error::InvalidDataUri => write!(f, "Invalid data uri"),
+            Error::FailedToDecodeBase64(e) => write!(f, "Failed to decode base64 data: {}", e),
+            Error::GltfParse(e) => write!(f, "Failed to parse glTF: {}", e),
+            Error::MissingBinaryChunk => write!(f, "glTF buffer refers to a missing GLB chunk"),
+            Error::MissingAttribute {
+                mesh,
+                primitive,
+                attribute,
+            } => write!(
+                f,
+                "Primitive {} of mesh {} is missing attribute {}",
+                primitive, mesh, attribute
+            ),
+            Error::InvalidAccessor {
+                mesh,
+                primitive,
+                attribute,
+                message,
+            } => write!(
+                f,
+                "Attribute {} of primitive {} of mesh {} is invalid: {}",
+                attribute, primitive, mesh, message
+            ),
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => write!(
+                f,
+                "Failed to parse {} at line {}: {}",
+                path.display(),
+                line,
+                message
+            ),
+            Error::InvalidData { path, message } => {
+                write!(f, "Invalid data in {}: {}", path.display(), message)
+            }
+            Error::MissingAnimationData { animation, channel } => write!(
+                f,
+                "Channel {} of animation {} is missing its keyframe data",
+                channel, animation
+            ),
+            Error::UnsupportedFeature(feature) => write!(f, "Unsupported feature: {}", feature),
+            Error::Texture(e) => write!(f, "Failed to load texture: {}", e),
+            Error::LoaderStopped => write!(f, "The loader threads have stopped"),
+            Error::ReaderPanicked(message) => write!(f, "Reading the model panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        Error::Io(other)
+    }
+}
+impl From<std::ffi::NulError> for Error {
+    fn from(other: std::ffi::NulError) -> Self {
+        Error::FileContainsNil(other)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(other: ImageError) -> Self {
+        Error::FailedToDecodeImage(other)
+    }
+}
+
+impl From<gltf::Error> for Error {
+    fn from(other: gltf::Error) -> Self {
+        Error::GltfParse(other)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(other: base64::DecodeError) -> Self {
+        Error::FailedToDecodeBase64(other)
+    }
+}
+
+/// Environment variable listing extra asset directories, separated like `PATH`. They are
+/// searched before the default roots.
+pub const ASSET_PATH_VAR: &str = "RENDERER_ASSET_PATH";
+
+/// Pack loaded from the directory of the executable, if present, so a build can ship its
+/// assets as a single file.
+pub const ASSET_PACK_NAME: &str = "assets.tar";
+
+/// Reads and decodes asset files through a list of mounts, given as paths relative to the
+/// mounts. Earlier mounts shadow the files of later ones. Absolute paths are read from disk
+/// directly. Readers never touch the GL context, so clones of them run on worker threads.
+#[derive(Clone, Default)]
+pub struct AssetReader {
+    mounts: Vec<Arc<dyn Mount>>,
+}
+
+impl AssetReader {
+    pub fn new() -> AssetReader {
+        AssetReader::default()
+    }
+
+    /// Adds a mount searched after the existing ones.
+    pub fn with_mount<M: Mount + 'static>(mut self, mount: M) -> AssetReader {
+        self.mounts.push(Arc::new(mount));
+        self
+    }
+
+    pub fn load_binary(&self, resource_path: &Path) -> Result<Vec<u8>, Error> {
+        if resource_path.is_absolute() {
+            return Ok(fs::read(resource_path)?);
+        }
+
+        let path = vfs::normalize(resource_path);
+
+        match self.mounts.iter().find(|mount| mount.contains(&path)) {
+            Some(mount) => Ok(mount.read(&path)?),
+            None => Err(Error::NotFound {
+                path,
+                searched: self.mounts.iter().map(|mount| mount.describe()).collect(),
+            }),
+        }
+    }
+
+    /// When the file that `load_binary` would read was last modified, if the mount knows.
+    pub fn modified(&self, resource_path: &Path) -> Option<SystemTime> {
+        if resource_path.is_absolute() {
+            return fs::metadata(resource_path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+        }
+
+        let path = vfs::normalize(resource_path);
+
+        self.mounts
+            .iter()
+            .find(|mount| mount.contains(&path))
+            .and_then(|mount| mount.modified(&path))
+    }
+
+    /// Decodes an image file. Radiance and OpenEXR files are decoded as floating point.
+    pub fn load_image(&self, resource_path: &Path) -> Result<TextureImage, Error> {
+        let data = self.load_binary(resource_path)?;
+        let img = image::load_from_memory(&data)?;
+
+        Ok(TextureImage::from_dynamic(img))
+    }
+
+    /// Reads a model in any of the supported formats, chosen by the file extension. Files
+    /// without a known extension are read as glTF.
+    pub fn read_model(&self, resource_path: &str) -> Result<ModelData, Error> {
+        match Path::new(resource_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("obj") => self.read_obj(resource_path),
+            Some("stl") => self.read_stl(resource_path),
+            Some("ply") => self.read_ply(resource_path),
+            _ => self.read_gltf(resource_path),
+        }
+    }
+}
+
+/// Loads assets for the GL thread, sharing the uploaded textures and meshes through the
+/// asset cache.
+pub struct ResourceLoader {
+    reader: AssetReader,
+    cache: AssetCache,
+}
+
+impl ResourceLoader {
+    /// Mounts the directories of `RENDERER_ASSET_PATH`, the source tree in debug builds, then
+    /// the directory of the executable, where the build copies the assets, the asset pack next
+    /// to the executable and the working directory. The shaders are compiled in as a last
+    /// resort.
+    pub fn from_default_roots() -> Result<ResourceLoader, Error> {
+        let exe_file_name = env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
+        let exe_path = exe_file_name.parent().ok_or(Error::FailedToGetExePath)?;
+
+        let mut reader = AssetReader::new();
+
+        if let Some(asset_path) = env::var_os(ASSET_PATH_VAR) {
+            for root in env::split_paths(&asset_path) {
+                reader = reader.with_mount(DirectoryMount::new(root));
+            }
+        }
+
+        // Debug builds read the assets from the source tree before the copies made by the
+        // build, so edited assets can be reloaded without rebuilding
+        if cfg!(debug_assertions) {
+            reader = reader.with_mount(DirectoryMount::new(env!("CARGO_MANIFEST_DIR")));
+        }
+
+        reader = reader.with_mount(DirectoryMount::new(exe_path));
+
+        let pack_path = exe_path.join(ASSET_PACK_NAME);
+        if pack_path.is_file() {
+            reader = reader.with_mount(PackMount::open(pack_path)?);
+        }
+
+        if let Ok(current_dir) = env::current_dir() {
+            if current_dir != exe_path {
+                reader = reader.with_mount(DirectoryMount::new(current_dir));
+            }
+        }
+
+        let shaders = MemoryMount::new()
+            .with_file(
+                "assets/shaders/shader.vert",
+                include_bytes!("../../assets/shaders/shader.vert").as_slice(),
+            )
+            .with_file(
+                "assets/shaders/shader.frag",
+                include_bytes!("../../assets/shaders/shader.frag").as_slice(),
+            )
+            .with_file(
+                "assets/shaders/skybox.vert",
+                include_bytes!("../../assets/shaders/skybox.vert").as_slice(),
+            )
+            .with_file(
+                "assets/shaders/skybox.frag",
+                include_bytes!("../../assets/shaders/skybox.frag").as_slice(),
+            );
+
+        Ok(ResourceLoader::new(reader.with_mount(shaders)))
+    }
+
+    pub fn new(reader: AssetReader) -> ResourceLoader {
+        ResourceLoader {
+            reader,
+            cache: AssetCache::new(),
+        }
+    }
+
+    pub fn reader(&self) -> &AssetReader {
+        &self.reader
+    }
+
+    /// Textures and meshes shared by the loaded models.
+    pub fn cache(&self) -> &AssetCache {
+        &self.cache
+    }
+
+    pub fn load_binary(&self, resource_path: &Path) -> Result<Vec<u8>, Error> {
+        self.reader.load_binary(resource_path)
+    }
+
+    /// Decodes an image file again and replaces the pixels of the cached textures created
+    /// from it, so every material using them shows the new image. Returns how many textures
+    /// were updated, the textures are left unchanged when decoding fails.
+    pub fn reload_textures(&self, resource_path: &Path) -> Result<usize, Error> {
+        let textures = self.cache.textures(&AssetKey::path(resource_path));
+        if textures.is_empty() {
+            return Ok(0);
+        }
+
+        let img = self.reader.load_image(resource_path)?;
+        for texture in &textures {
+            texture.replace_image(&img);
+        }
+
+        Ok(textures.len())
+    }
+
+    pub fn load_cstring(&self, resource_path: &str) -> Result<ffi::CString, Error> {
+        let buffer = self.load_binary(Path::new(resource_path))?;
+
+        let result = ffi::CString::new(buffer)?;
+
+        Ok(result)
+    }
+
+    // Todo make a more flexible load_iamge function that can load multiple formats
+    pub fn load_jpg(
+        &self,
+        resource_path: &str,
+    ) -> Result<ImageBuffer<image::Rgb<u8>, Vec<u8>>, Error> {
+        let data = self.load_binary(Path::new(resource_path))?;
+        let img = image::load_from_memory(&data)?.to_rgb8();
+
+        Ok(img)
+    }
+
+    /// Loads an image as 32-bit float RGB, keeping the range of Radiance and OpenEXR files.
+    /// 8-bit images are mapped to 0..1.
+    pub fn load_hdr(&self, resource_path: &str) -> Result<Rgb32FImage, Error> {
+        let data = self.load_binary(Path::new(resource_path))?;
+        let img = image::load_from_memory(&data)?.into_rgb32f();
+
+        Ok(img)
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::animation::Animation;
+use crate::material::{AlphaMode, CullMode, Material, MaterialFactors, MaterialTextures};
+use crate::mesh::{Mesh, MeshVertex, MorphTarget, MorphTargets, SkinnedMeshVertex, Topology};
+use crate::model::{Model, Node, Primitive, Skin};
+use crate::shader::Program;
+use crate::texture::{ColorSpace, DefaultTextures, SamplerDesc, Texture, TextureImage};
+
+use super::{AssetCache, AssetKey};
+
+/// Decoded image waiting to be uploaded as a texture.
+pub struct ImageData {
+    pub key: AssetKey,
+    pub image: TextureImage,
+    pub sampler: SamplerDesc,
+    pub color_space: ColorSpace,
+}
+
+impl ImageData {
+    /// Size of the pixel data in bytes.
+    pub fn size(&self) -> usize {
+        self.image.size()
+    }
+}
+
+pub enum Vertices {
+    Static(Vec<MeshVertex>),
+    Skinned(Vec<SkinnedMeshVertex>),
+}
+
+/// Vertex data of a primitive waiting to be uploaded as a mesh. Meshes are cached by the key
+/// of their file and their mesh and primitive index in it.
+pub struct MeshData {
+    pub key: AssetKey,
+    pub mesh_index: usize,
+    pub primitive_index: usize,
+    pub vertices: Vertices,
+    pub indices: Vec<i32>,
+    pub topology: Topology,
+    /// Displacements of the morph targets, empty when the mesh has none.
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+impl MeshData {
+    /// Size of the vertex, index and morph target data in bytes.
+    pub fn size(&self) -> usize {
+        let vertices = match &self.vertices {
+            Vertices::Static(vertices) => std::mem::size_of_val(vertices.as_slice()),
+            Vertices::Skinned(vertices) => std::mem::size_of_val(vertices.as_slice()),
+        };
+        let morph_targets: usize = self
+            .morph_targets
+            .iter()
+            .map(|target| {
+                std::mem::size_of_val(target.positions.as_slice())
+                    + std::mem::size_of_val(target.normals.as_slice())
+                    + std::mem::size_of_val(target.tangents.as_slice())
+            })
+            .sum();
+
+        vertices + std::mem::size_of_val(self.indices.as_slice()) + morph_targets
+    }
+
+    fn create(self, gl: &gl::Gl) -> Mesh {
+        let mesh = match self.vertices {
+            Vertices::Static(vertices) => {
+                Mesh::create_with_topology(gl, vertices, self.indices, self.topology)
+            }
+            Vertices::Skinned(vertices) => {
+                Mesh::create_with_topology(gl, vertices, self.indices, self.topology)
+            }
+        };
+
+        match self.morph_targets.is_empty() {
+            true => mesh,
+            false => mesh.with_morph_targets(MorphTargets::new(gl, &self.morph_targets)),
+        }
+    }
+}
+
+pub struct PrimitiveData {
+    pub mesh: MeshData,
+    pub material_index: usize,
+}
+
+/// Material referring to the images of its model by index. Missing textures are replaced by
+/// the default textures when uploading.
+pub struct MaterialData {
+    pub textures: MaterialTextures<Option<usize>>,
+    pub factors: MaterialFactors,
+    pub alpha_mode: AlphaMode,
+    pub cull_mode: CullMode,
+}
+
+/// Model read and decoded by an `AssetReader`, with everything the GL thread needs to upload
+/// it. Unlike `Model` it can be sent between threads.
+pub struct ModelData {
+    /// Files the model was read from besides its image files, like glTF buffers and MTL
+    /// libraries.
+    pub sources: Vec<PathBuf>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+    pub meshes: Vec<Vec<PrimitiveData>>,
+    pub images: Vec<ImageData>,
+    pub materials: Vec<MaterialData>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
+}
+
+impl ModelData {
+    /// Every file the model was read from, including the image files.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.sources.clone();
+
+        for image in &self.images {
+            if let AssetKey::Path(path) = &image.key {
+                if !files.contains(path) {
+                    files.push(path.clone());
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Starts uploading the model, one image or mesh at a time.
+    pub fn upload(self) -> ModelUpload {
+        let mesh_count = self.meshes.len();
+        let primitives = self
+            .meshes
+            .into_iter()
+            .enumerate()
+            .flat_map(|(mesh, primitives)| {
+                primitives
+                    .into_iter()
+                    .map(move |primitive| (mesh, primitive))
+            })
+            .collect();
+
+        ModelUpload {
+            images: self.images.into(),
+            primitives,
+            textures: Vec::new(),
+            meshes: (0..mesh_count).map(|_| Vec::new()).collect(),
+            nodes: self.nodes,
+            roots: self.roots,
+            materials: self.materials,
+            skins: self.skins,
+            animations: self.animations,
+        }
+    }
+}
+
+/// Upload of a model that can be spread over several frames.
+pub struct ModelUpload {
+    images: VecDeque<ImageData>,
+    /// Primitives left to upload, along with the index of their mesh.
+    primitives: VecDeque<(usize, PrimitiveData)>,
+    textures: Vec<Rc<Texture>>,
+    meshes: Vec<Vec<Primitive>>,
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    materials: Vec<MaterialData>,
+    skins: Vec<Skin>,
+    animations: Vec<Animation>,
+}
+
+impl ModelUpload {
+    /// Uploads the next image or mesh and returns the number of bytes uploaded, which is
+    /// zero when the asset was already cached. Returns `None` once everything is uploaded.
+    pub fn step(&mut self, gl: &gl::Gl, cache: &AssetCache) -> Option<usize> {
+        let mut uploaded = 0;
+
+        if let Some(image) = self.images.pop_front() {
+            let size = image.size();
+            let ImageData {
+                key,
+                image,
+                sampler,
+                color_space,
+            } = image;
+
+            let texture = cache.texture(key, color_space, &sampler, || {
+                uploaded = size;
+                Texture::new(gl, image, &sampler, color_space)
+            });
+            self.textures.push(texture);
+
+            return Some(uploaded);
+        }
+
+        let (mesh_index, primitive) = self.primitives.pop_front()?;
+        let data = primitive.mesh;
+        let skinned = matches!(data.vertices, Vertices::Skinned(_));
+
+        let mesh = cache.mesh(
+            data.key.clone(),
+            data.mesh_index,
+            data.primitive_index,
+            || {
+                uploaded = data.size();
+                data.create(gl)
+            },
+        );
+        self.meshes[mesh_index].push(Primitive::new(mesh, primitive.material_index, skinned));
+
+        Some(uploaded)
+    }
+
+    /// Uploads whatever is left and builds the model.
+    pub fn finish<'a>(
+        mut self,
+        gl: &gl::Gl,
+        cache: &AssetCache,
+        program: &'a Program,
+        defaults: &DefaultTextures,
+    ) -> Model<'a> {
+        while self.step(gl, cache).is_some() {}
+
+        let textures = self.textures;
+        let texture_or = |index: Option<usize>, default: &Rc<Texture>| match index {
+            Some(index) => textures[index].clone(),
+            None => default.clone(),
+        };
+
+        let materials = self
+            .materials
+            .into_iter()
+            .map(|material| {
+                let indices = material.textures;
+                let textures = MaterialTextures {
+                    base_color: texture_or(indices.base_color, &defaults.white),
+                    metallic_roughness: texture_or(indices.metallic_roughness, &defaults.white),
+                    normal: texture_or(indices.normal, &defaults.flat_normal),
+                    occlusion: texture_or(indices.occlusion, &defaults.white),
+                    emissive: texture_or(indices.emissive, &defaults.white),
+                    transforms: indices.transforms,
+                };
+
+                Material::new(
+                    program,
+                    textures,
+                    material.factors,
+                    material.alpha_mode,
+                    material.cull_mode,
+                )
+            })
+            .collect();
+
+        Model::new(
+            gl,
+            self.nodes,
+            self.roots,
+            self.meshes,
+            materials,
+            self.skins,
+            self.animations,
+        )
+    }
+}
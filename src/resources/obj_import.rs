@@ -0,0 +1,658 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use cgmath::{One, Point3, Quaternion, Vector2, Vector3, Zero};
+
+use crate::material::{AlphaMode, CullMode, MaterialFactors, MaterialTextures, TextureTransforms};
+use crate::mesh::{generate, MeshVertex, Topology};
+use crate::model::Node;
+use crate::texture::{ColorSpace, SamplerDesc};
+
+use super::{
+    AssetKey, AssetReader, Error, ImageData, MaterialData, MeshData, ModelData, PrimitiveData,
+    Vertices,
+};
+
+/// Corner of a face, given as zero based indices into the vertex data of the file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Triangles of a group sharing a material.
+struct FaceSet {
+    material: Option<String>,
+    triangles: Vec<[FaceVertex; 3]>,
+}
+
+struct ObjData {
+    positions: Vec<Point3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    normals: Vec<Vector3<f32>>,
+    /// Face sets of every group, in file order.
+    groups: Vec<Vec<FaceSet>>,
+    material_libraries: Vec<String>,
+}
+
+/// Phong material as described by an MTL file, with texture paths relative to the resource
+/// root.
+struct MtlMaterial {
+    diffuse: Vector3<f32>,
+    specular: Vector3<f32>,
+    shininess: f32,
+    dissolve: f32,
+    diffuse_map: Option<PathBuf>,
+    bump_map: Option<PathBuf>,
+    bump_scale: f32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Vector3::new(1.0, 1.0, 1.0),
+            specular: Vector3::zero(),
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_map: None,
+            bump_map: None,
+            bump_scale: 1.0,
+        }
+    }
+}
+
+impl AssetReader {
+    /// Reads a Wavefront OBJ model along with the materials of its MTL libraries. Every group
+    /// becomes a node, with one primitive for each material used by the group.
+    pub fn read_obj(&self, resource_path: &str) -> Result<ModelData, Error> {
+        let path = Path::new(resource_path);
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let source = String::from_utf8_lossy(&self.load_binary(path)?).into_owned();
+        let obj = parse_obj(path, &source)?;
+
+        let mut sources = vec![path.to_path_buf()];
+        let mut mtl_materials = HashMap::new();
+        for library in &obj.material_libraries {
+            let library_path = directory.join(library);
+            let source = String::from_utf8_lossy(&self.load_binary(&library_path)?).into_owned();
+            mtl_materials.extend(parse_mtl(&library_path, &source)?);
+            sources.push(library_path);
+        }
+
+        // Faces without a material, or with one missing from the libraries, use the default
+        let default_material = MtlMaterial::default();
+
+        let key = AssetKey::path(path);
+        let mut material_indices: HashMap<Option<&str>, usize> = HashMap::new();
+        let mut materials = Vec::new();
+        let mut images = Vec::new();
+        let mut meshes = Vec::new();
+
+        for (group_index, group) in obj.groups.iter().enumerate() {
+            let mut primitives = Vec::new();
+
+            for (face_set_index, face_set) in group.iter().enumerate() {
+                let name = face_set
+                    .material
+                    .as_deref()
+                    .filter(|name| mtl_materials.contains_key(*name));
+
+                let material_index = match material_indices.get(&name) {
+                    Some(index) => *index,
+                    None => {
+                        let mtl = name.map_or(&default_material, |name| &mtl_materials[name]);
+                        let material = self.load_mtl_material(mtl, &mut images)?;
+
+                        materials.push(material);
+                        material_indices.insert(name, materials.len() - 1);
+                        materials.len() - 1
+                    }
+                };
+
+                primitives.push(PrimitiveData {
+                    mesh: load_mesh(&key, group_index, face_set_index, &obj, &face_set.triangles),
+                    material_index,
+                });
+            }
+
+            meshes.push(primitives);
+        }
+
+        let nodes: Vec<Node> = (0..meshes.len())
+            .map(|mesh| {
+                Node::new(
+                    Some(mesh),
+                    None,
+                    Vec::new(),
+                    Vector3::zero(),
+                    Quaternion::one(),
+                    Vector3::new(1.0, 1.0, 1.0),
+                    Vec::new(),
+                )
+            })
+            .collect();
+        let roots = (0..nodes.len()).collect();
+
+        Ok(ModelData {
+            sources,
+            nodes,
+            roots,
+            meshes,
+            images,
+            materials,
+            skins: Vec::new(),
+            animations: Vec::new(),
+        })
+    }
+
+    /// Converts a Phong material to a metallic-roughness one. Textures shared between
+    /// materials are only decoded once.
+    fn load_mtl_material(
+        &self,
+        mtl: &MtlMaterial,
+        images: &mut Vec<ImageData>,
+    ) -> Result<MaterialData, Error> {
+        let mut load_texture = |path: &Option<PathBuf>, color_space| {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+
+            let key = AssetKey::path(path);
+            if let Some(index) = images
+                .iter()
+                .position(|image| image.key == key && image.color_space == color_space)
+            {
+                return Ok(Some(index));
+            }
+
+            images.push(ImageData {
+                key,
+                image: self.load_image(path)?,
+                sampler: SamplerDesc::default(),
+                color_space,
+            });
+
+            Ok::<_, Error>(Some(images.len() - 1))
+        };
+
+        let textures = MaterialTextures {
+            base_color: load_texture(&mtl.diffuse_map, ColorSpace::Srgb)?,
+            metallic_roughness: None,
+            normal: load_texture(&mtl.bump_map, ColorSpace::Linear)?,
+            occlusion: None,
+            emissive: None,
+            transforms: TextureTransforms::default(),
+        };
+
+        // Map the Phong exponent to a GGX roughness, materials without a specular color
+        // are fully rough. Phong has no notion of metalness, so everything is dielectric.
+        let specular = mtl.specular.x.max(mtl.specular.y).max(mtl.specular.z);
+        let roughness = match specular > 0.0 {
+            true => (2.0 / (mtl.shininess.max(0.0) + 2.0)).sqrt().sqrt(),
+            false => 1.0,
+        };
+
+        let factors = MaterialFactors {
+            base_color: mtl.diffuse.extend(mtl.dissolve),
+            metallic: 0.0,
+            roughness,
+            normal_scale: mtl.bump_scale,
+            ..MaterialFactors::default()
+        };
+
+        let alpha_mode = match mtl.dissolve < 1.0 {
+            true => AlphaMode::Blend,
+            false => AlphaMode::Opaque,
+        };
+
+        Ok(MaterialData {
+            textures,
+            factors,
+            alpha_mode,
+            cull_mode: CullMode::Back,
+        })
+    }
+}
+
+fn load_mesh(
+    key: &AssetKey,
+    group_index: usize,
+    face_set_index: usize,
+    obj: &ObjData,
+    triangles: &[[FaceVertex; 3]],
+) -> MeshData {
+    // Corners sharing all of their indices become a single vertex
+    let mut vertex_indices: HashMap<FaceVertex, u32> = HashMap::new();
+    let mut corners = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for corner in triangles.iter().flatten() {
+        let index = *vertex_indices.entry(*corner).or_insert_with(|| {
+            corners.push(*corner);
+            (corners.len() - 1) as u32
+        });
+        indices.push(index);
+    }
+
+    let mut positions: Vec<Point3<f32>> = corners
+        .iter()
+        .map(|corner| obj.positions[corner.position])
+        .collect();
+    // Corners without texture coordinates all map to the origin of the texture
+    let mut uvs: Vec<Vector2<f32>> = corners
+        .iter()
+        .map(|corner| corner.uv.map_or(Vector2::zero(), |uv| obj.uvs[uv]))
+        .collect();
+    let normals: Option<Vec<Vector3<f32>>> = corners
+        .iter()
+        .map(|corner| {
+            corner
+                .normal
+                .map(|normal| generate::normalize_or(obj.normals[normal], Vector3::unit_y()))
+        })
+        .collect();
+
+    // Generate flat normals when any corner is missing one, which requires every triangle
+    // to have its own vertices
+    let normals = match normals {
+        Some(normals) => normals,
+        None => {
+            positions = generate::unweld(&positions, &indices);
+            uvs = generate::unweld(&uvs, &indices);
+            indices = (0..indices.len() as u32).collect();
+
+            generate::flat_normals(&positions)
+        }
+    };
+
+    let tangents = generate::tangents(&positions, &normals, &uvs, &indices);
+
+    let vertices = positions
+        .into_iter()
+        .zip(uvs)
+        .zip(normals)
+        .zip(tangents)
+        .map(|(((position, uv), normal), tangent)| {
+            let bitangent = generate::bitangent(normal, tangent);
+
+            MeshVertex::new(position, uv, normal, tangent.truncate(), bitangent)
+        })
+        .collect();
+
+    let indices = indices.into_iter().map(|index| index as i32).collect();
+
+    MeshData {
+        key: key.clone(),
+        mesh_index: group_index,
+        primitive_index: face_set_index,
+        vertices: Vertices::Static(vertices),
+        indices,
+        topology: Topology::Triangles,
+        morph_targets: Vec::new(),
+    }
+}
+
+fn parse_obj(path: &Path, source: &str) -> Result<ObjData, Error> {
+    let mut obj = ObjData {
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        groups: vec![Vec::new()],
+        material_libraries: Vec::new(),
+    };
+    let mut material = None;
+
+    for (index, line) in source.lines().enumerate() {
+        parse_obj_line(&mut obj, &mut material, line).map_err(|message| Error::Parse {
+            path: path.to_path_buf(),
+            line: index + 1,
+            message,
+        })?;
+    }
+
+    obj.groups.retain(|group| !group.is_empty());
+
+    Ok(obj)
+}
+
+fn parse_obj_line(
+    obj: &mut ObjData,
+    material: &mut Option<String>,
+    line: &str,
+) -> Result<(), String> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let mut tokens = line.split_whitespace();
+
+    let Some(keyword) = tokens.next() else {
+        return Ok(());
+    };
+    let rest = line[keyword.len()..].trim();
+
+    match keyword {
+        "v" => obj.positions.push(parse_floats::<3>(&mut tokens)?.into()),
+        "vt" => {
+            let u = parse_float(tokens.next())?;
+            let v = tokens.next().map_or(Ok(0.0), |v| parse_float(Some(v)))?;
+
+            // OBJ puts the origin of textures in the bottom left corner
+            obj.uvs.push(Vector2::new(u, 1.0 - v));
+        }
+        "vn" => obj.normals.push(parse_floats::<3>(&mut tokens)?.into()),
+        "f" => {
+            let corners = tokens
+                .map(|token| parse_face_vertex(token, obj))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            if corners.len() < 3 {
+                return Err(format!("face with {} vertices", corners.len()));
+            }
+
+            // Triangulate polygons as a fan around their first corner
+            let triangles = corners
+                .windows(2)
+                .skip(1)
+                .map(|pair| [corners[0], pair[0], pair[1]]);
+
+            let group = obj.groups.last_mut().unwrap();
+            match group.last_mut() {
+                Some(face_set) if face_set.material == *material => {
+                    face_set.triangles.extend(triangles)
+                }
+                _ => group.push(FaceSet {
+                    material: material.clone(),
+                    triangles: triangles.collect(),
+                }),
+            }
+        }
+        "g" | "o" if !obj.groups.last().unwrap().is_empty() => obj.groups.push(Vec::new()),
+        "usemtl" => *material = Some(rest.to_string()),
+        "mtllib" => obj
+            .material_libraries
+            .extend(tokens.map(|file| file.replace('\\', "/"))),
+        // Smoothing groups, lines, points and free-form geometry are ignored
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Parses a `position/uv/normal` face corner, where the uv and normal are optional.
+fn parse_face_vertex(token: &str, obj: &ObjData) -> Result<FaceVertex, String> {
+    let mut indices = token.split('/');
+
+    let position = resolve_index(indices.next(), obj.positions.len())?
+        .ok_or(format!("face vertex {} without a position", token))?;
+    let uv = resolve_index(indices.next(), obj.uvs.len())?;
+    let normal = resolve_index(indices.next(), obj.normals.len())?;
+
+    Ok(FaceVertex {
+        position,
+        uv,
+        normal,
+    })
+}
+
+/// Converts a one based index, or a negative index relative to the end, to a zero based one.
+fn resolve_index(token: Option<&str>, count: usize) -> Result<Option<usize>, String> {
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Ok(None);
+    };
+
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("invalid index {}", token))?;
+
+    let resolved = match index {
+        index if index > 0 => index - 1,
+        index => count as i64 + index,
+    };
+
+    match resolved >= 0 && resolved < count as i64 {
+        true => Ok(Some(resolved as usize)),
+        false => Err(format!("index {} is out of range", index)),
+    }
+}
+
+fn parse_mtl(path: &Path, source: &str) -> Result<HashMap<String, MtlMaterial>, Error> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let rest = line[keyword.len()..].trim();
+
+        let result = match (keyword, &mut current) {
+            ("newmtl", _) => {
+                materials.extend(current.take());
+                current = Some((rest.to_string(), MtlMaterial::default()));
+                Ok(())
+            }
+            (_, None) => Err(format!("{} before any newmtl", keyword)),
+            (keyword, Some((_, material))) => {
+                parse_mtl_statement(material, keyword, &mut tokens, rest, directory)
+            }
+        };
+
+        result.map_err(|message| Error::Parse {
+            path: path.to_path_buf(),
+            line: index + 1,
+            message,
+        })?;
+    }
+
+    materials.extend(current);
+
+    Ok(materials)
+}
+
+fn parse_mtl_statement<'t>(
+    material: &mut MtlMaterial,
+    keyword: &str,
+    tokens: &mut impl Iterator<Item = &'t str>,
+    rest: &str,
+    directory: &Path,
+) -> Result<(), String> {
+    match keyword {
+        "Kd" => material.diffuse = parse_floats::<3>(tokens)?.into(),
+        "Ks" => material.specular = parse_floats::<3>(tokens)?.into(),
+        "Ns" => material.shininess = parse_float(tokens.next())?,
+        "d" => material.dissolve = parse_float(tokens.next())?,
+        "Tr" => material.dissolve = 1.0 - parse_float(tokens.next())?,
+        "map_Kd" => {
+            let (file, _) = parse_texture_statement(rest)?;
+            material.diffuse_map = Some(directory.join(file));
+        }
+        "map_Bump" | "map_bump" | "bump" => {
+            let (file, bump_scale) = parse_texture_statement(rest)?;
+            material.bump_map = Some(directory.join(file));
+            material.bump_scale = bump_scale;
+        }
+        // Ambient, emissive and illumination model statements have no equivalent
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Parses the options and file name of a texture map statement, returning the file name and
+/// the bump multiplier. Other options are skipped.
+fn parse_texture_statement(rest: &str) -> Result<(String, f32), String> {
+    let mut tokens = rest.split_whitespace().peekable();
+    let mut bump_scale = 1.0;
+
+    while let Some(option) = tokens.next_if(|token| token.starts_with('-')) {
+        match option {
+            "-bm" => bump_scale = parse_float(tokens.next())?,
+            // Offset, scale and turbulence take up to three numbers
+            "-o" | "-s" | "-t" => {
+                for _ in 0..3 {
+                    tokens.next_if(|token| token.parse::<f32>().is_ok());
+                }
+            }
+            "-mm" => {
+                tokens.nth(1);
+            }
+            _ => {
+                tokens.next();
+            }
+        }
+    }
+
+    let file = tokens.collect::<Vec<_>>().join(" ");
+
+    match file.is_empty() {
+        true => Err("texture statement without a file".to_string()),
+        false => Ok((file.replace('\\', "/"), bump_scale)),
+    }
+}
+
+fn parse_float(token: Option<&str>) -> Result<f32, String> {
+    let token = token.ok_or("missing number")?;
+
+    token
+        .parse()
+        .map_err(|_| format!("invalid number {}", token))
+}
+
+fn parse_floats<'t, const N: usize>(
+    tokens: &mut impl Iterator<Item = &'t str>,
+) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+
+    for value in values.iter_mut() {
+        *value = parse_float(tokens.next())?;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::MemoryMount;
+
+    const QUAD: &str = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+";
+
+    /// Position indices of the triangles of every face set.
+    fn triangles(obj: &ObjData) -> Vec<Vec<[usize; 3]>> {
+        obj.groups
+            .iter()
+            .flatten()
+            .map(|face_set| {
+                face_set
+                    .triangles
+                    .iter()
+                    .map(|triangle| triangle.map(|corner| corner.position))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangulates_polygons_as_fans() {
+        let obj = parse_obj(Path::new("quad.obj"), &format!("{}f 1 2 3 4", QUAD)).unwrap();
+
+        assert_eq!(triangles(&obj), vec![vec![[0, 1, 2], [0, 2, 3]]]);
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        let obj = parse_obj(Path::new("quad.obj"), &format!("{}f -4 -3 -2 -1", QUAD)).unwrap();
+
+        assert_eq!(triangles(&obj), vec![vec![[0, 1, 2], [0, 2, 3]]]);
+    }
+
+    #[test]
+    fn reports_out_of_range_indices_with_their_line() {
+        let result = parse_obj(Path::new("quad.obj"), &format!("{}f 1 2 5", QUAD));
+
+        assert!(matches!(result, Err(Error::Parse { line: 6, .. })));
+    }
+
+    #[test]
+    fn splits_groups_by_material() {
+        let source = format!("{}usemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\n", QUAD);
+        let obj = parse_obj(Path::new("quad.obj"), &source).unwrap();
+
+        let materials: Vec<_> = obj.groups[0]
+            .iter()
+            .map(|face_set| face_set.material.as_deref())
+            .collect();
+        assert_eq!(materials, vec![Some("red"), Some("blue")]);
+        assert_eq!(triangles(&obj), vec![vec![[0, 1, 2]], vec![[0, 2, 3]]]);
+    }
+
+    #[test]
+    fn reads_materials_of_each_face_set() {
+        let obj = format!(
+            "mtllib quad.mtl\n{}usemtl red\nf 1 2 3\nusemtl glass\nf 1 3 4\n",
+            QUAD
+        );
+        let mtl = "newmtl red\nKd 1 0 0\nnewmtl glass\nKd 1 1 1\nd 0.5\n";
+        let reader = AssetReader::new().with_mount(
+            MemoryMount::new()
+                .with_file("models/quad.obj", obj)
+                .with_file("models/quad.mtl", mtl),
+        );
+
+        let model = reader.read_obj("models/quad.obj").unwrap();
+
+        assert_eq!(model.meshes.len(), 1);
+        assert_eq!(model.meshes[0].len(), 2);
+        assert_eq!(model.materials.len(), 2);
+        assert_eq!(
+            model.materials[0].factors.base_color,
+            cgmath::Vector4::new(1.0, 0.0, 0.0, 1.0)
+        );
+        assert!(matches!(model.materials[1].alpha_mode, AlphaMode::Blend));
+        assert_eq!(
+            model.sources,
+            vec![
+                PathBuf::from("models/quad.obj"),
+                PathBuf::from("models/quad.mtl")
+            ]
+        );
+    }
+
+    #[test]
+    fn replaces_zero_length_normals() {
+        let source = format!("{}vn 0 0 0\nf 1//1 2//1 3//1\n", QUAD);
+        let reader =
+            AssetReader::new().with_mount(MemoryMount::new().with_file("quad.obj", source));
+
+        let model = reader.read_obj("quad.obj").unwrap();
+
+        let Vertices::Static(vertices) = &model.meshes[0][0].mesh.vertices else {
+            panic!("OBJ meshes are static");
+        };
+        for vertex in vertices {
+            assert_eq!(vertex.normal(), Vector3::unit_y());
+            assert!(vertex.tangent().x.is_finite());
+        }
+    }
+
+    #[test]
+    fn parses_texture_options() {
+        let mtl = "newmtl bumpy\nmap_Kd -s 2 2 1 albedo.png\nmap_Bump -bm 0.5 normal.png\n";
+        let materials = parse_mtl(Path::new("models/bumpy.mtl"), mtl).unwrap();
+
+        let bumpy = &materials["bumpy"];
+        assert_eq!(bumpy.diffuse_map, Some(PathBuf::from("models/albedo.png")));
+        assert_eq!(bumpy.bump_map, Some(PathBuf::from("models/normal.png")));
+        assert_eq!(bumpy.bump_scale, 0.5);
+    }
+}
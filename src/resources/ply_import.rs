@@ -0,0 +1,550 @@
+use std::path::Path;
+
+use cgmath::{Point3, Vector3, Vector4};
+
+use crate::mesh::{generate, Topology};
+
+use super::scan_import::{scan_model, ScanMesh};
+use super::{AssetReader, Error, ModelData};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// Value mapped to 1.0 when the type stores a normalized color.
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::U8 => u8::MAX as f64,
+            ScalarType::U16 => u16::MAX as f64,
+            _ => 1.0,
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, name: &str) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| property.name == name)
+    }
+}
+
+/// Reads the values of the body of the file, which are either whitespace separated numbers
+/// or packed binary scalars.
+enum Body<'d> {
+    Ascii(std::str::SplitAsciiWhitespace<'d>),
+    Binary {
+        data: &'d [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar_type: ScalarType) -> Result<f64, String> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of data")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("invalid number {}", token))
+            }
+            Body::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let size = scalar_type.size();
+                let bytes = data
+                    .get(*offset..*offset + size)
+                    .ok_or("unexpected end of data")?;
+                *offset += size;
+
+                // Bring the bytes to little endian order
+                let mut buffer = [0; 8];
+                buffer[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+
+                Ok(match scalar_type {
+                    ScalarType::I8 => b0 as i8 as f64,
+                    ScalarType::U8 => b0 as f64,
+                    ScalarType::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+}
+
+impl AssetReader {
+    /// Reads an ASCII or binary PLY file with vertex positions and optionally normals, colors
+    /// and faces. Files without faces are drawn as point clouds, and faces without normals
+    /// get smooth normals.
+    pub fn read_ply(&self, resource_path: &str) -> Result<ModelData, Error> {
+        let path = Path::new(resource_path);
+        let data = self.load_binary(path)?;
+
+        let mesh = parse_ply(path, &data)?;
+
+        Ok(scan_model(path, mesh))
+    }
+}
+
+fn parse_ply(path: &Path, data: &[u8]) -> Result<ScanMesh, Error> {
+    let invalid_data = |message: String| Error::InvalidData {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let header_end = data
+        .windows(b"end_header".len())
+        .position(|window| window == b"end_header")
+        .ok_or_else(|| invalid_data("missing end_header".to_string()))?;
+    let body_start = data[header_end..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(data.len(), |newline| header_end + newline + 1);
+
+    let header = String::from_utf8_lossy(&data[..header_end]);
+    let (format, elements) = parse_header(path, &header)?;
+
+    let mut body = match format {
+        Format::Ascii => Body::Ascii(
+            std::str::from_utf8(&data[body_start..])
+                .map_err(|_| invalid_data("ASCII body is not valid text".to_string()))?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian | Format::BinaryBigEndian => Body::Binary {
+            data: &data[body_start..],
+            offset: 0,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(
+                element,
+                &mut body,
+                &mut positions,
+                &mut normals,
+                &mut colors,
+            ),
+            "face" => read_faces(element, &mut body, &mut indices),
+            _ => read_rows(element, &mut body, None, |_, _| {}),
+        }
+        .map_err(|message| invalid_data(format!("{} element: {}", element.name, message)))?;
+    }
+
+    if let Some(index) = indices
+        .iter()
+        .find(|index| **index as usize >= positions.len())
+    {
+        return Err(invalid_data(format!(
+            "vertex index {} is out of range",
+            index
+        )));
+    }
+
+    let topology = match indices.is_empty() {
+        true => Topology::Points,
+        false => Topology::Triangles,
+    };
+
+    let normals = match (normals.len() == positions.len(), topology) {
+        (true, _) => normals,
+        (false, Topology::Triangles) => generate::smooth_normals(&positions, &indices),
+        // Point clouds without normals are lit as if they were facing up
+        (false, Topology::Points) => vec![Vector3::unit_y(); positions.len()],
+    };
+
+    if topology == Topology::Points {
+        indices = (0..positions.len() as u32).collect();
+    }
+
+    Ok(ScanMesh {
+        colors: (colors.len() == positions.len()).then_some(colors),
+        positions,
+        normals,
+        indices,
+        topology,
+    })
+}
+
+fn parse_header(path: &Path, header: &str) -> Result<(Format, Vec<Element>), Error> {
+    let mut lines = header.lines().enumerate();
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    let error = |line: usize, message: String| Error::Parse {
+        path: path.to_path_buf(),
+        line: line + 1,
+        message,
+    };
+
+    if lines.next().map(|(_, line)| line.trim()) != Some("ply") {
+        return Err(error(0, "missing ply magic number".to_string()));
+    }
+
+    for (index, line) in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(index, format!("invalid element count {}", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (ScalarType::parse(count), ScalarType::parse(item))
+                else {
+                    return Err(error(index, format!("invalid list types in {}", line)));
+                };
+
+                elements
+                    .last_mut()
+                    .ok_or_else(|| error(index, "property before any element".to_string()))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: PropertyKind::List { count, item },
+                    });
+            }
+            ["property", scalar_type, name] => {
+                let scalar_type = ScalarType::parse(scalar_type)
+                    .ok_or_else(|| error(index, format!("invalid type {}", scalar_type)))?;
+
+                elements
+                    .last_mut()
+                    .ok_or_else(|| error(index, "property before any element".to_string()))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: PropertyKind::Scalar(scalar_type),
+                    });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error(index, format!("unknown header line {}", line))),
+        }
+    }
+
+    let format = format.ok_or_else(|| error(0, "missing format".to_string()))?;
+
+    Ok((format, elements))
+}
+
+/// Reads every row of an element, calling `row` with the scalar values and the items of the
+/// list property at index `list_property`, if any.
+fn read_rows(
+    element: &Element,
+    body: &mut Body,
+    list_property: Option<usize>,
+    mut row: impl FnMut(&[f64], &[f64]),
+) -> Result<(), String> {
+    let mut scalars = Vec::with_capacity(element.properties.len());
+    let mut list = Vec::new();
+
+    for _ in 0..element.count {
+        scalars.clear();
+        list.clear();
+
+        for (index, property) in element.properties.iter().enumerate() {
+            match property.kind {
+                PropertyKind::Scalar(scalar_type) => scalars.push(body.read(scalar_type)?),
+                PropertyKind::List { count, item } => {
+                    // Keep the scalar indices of the other properties unchanged
+                    scalars.push(0.0);
+
+                    for _ in 0..body.read(count)? as usize {
+                        let value = body.read(item)?;
+                        if list_property == Some(index) {
+                            list.push(value);
+                        }
+                    }
+                }
+            }
+        }
+
+        row(&scalars, &list);
+    }
+
+    Ok(())
+}
+
+fn read_vertices(
+    element: &Element,
+    body: &mut Body,
+    positions: &mut Vec<Point3<f32>>,
+    normals: &mut Vec<Vector3<f32>>,
+    colors: &mut Vec<Vector4<f32>>,
+) -> Result<(), String> {
+    let find = |names: [&str; 3]| -> Option<[usize; 3]> {
+        let [x, y, z] = names.map(|name| element.property(name));
+        Some([x?, y?, z?])
+    };
+
+    let position = find(["x", "y", "z"]).ok_or("missing x, y or z property")?;
+    let normal = find(["nx", "ny", "nz"]);
+    let color = find(["red", "green", "blue"]);
+    let alpha = element.property("alpha");
+
+    let color_scale = |index: usize| match element.properties[index].kind {
+        PropertyKind::Scalar(scalar_type) => scalar_type.color_scale(),
+        PropertyKind::List { .. } => 1.0,
+    };
+
+    read_rows(element, body, None, |values, _| {
+        let [x, y, z] = position.map(|index| values[index] as f32);
+        positions.push(Point3::new(x, y, z));
+
+        if let Some(normal) = normal {
+            let [x, y, z] = normal.map(|index| values[index] as f32);
+            normals.push(generate::normalize_or(
+                Vector3::new(x, y, z),
+                Vector3::unit_y(),
+            ));
+        }
+
+        if let Some(color) = color {
+            // Colors are stored in sRGB, while vertex colors are linear
+            let [r, g, b] = color.map(|index| srgb_to_linear(values[index] / color_scale(index)));
+            let a = alpha.map_or(1.0, |index| values[index] / color_scale(index));
+            colors.push(Vector4::new(r, g, b, a as f32));
+        }
+    })
+}
+
+/// Reads polygons given as lists of vertex indices, triangulated as fans around their first
+/// vertex.
+fn read_faces(element: &Element, body: &mut Body, indices: &mut Vec<u32>) -> Result<(), String> {
+    // Faces can have other list properties, like texture coordinates, before their indices
+    let property = element
+        .property("vertex_indices")
+        .or_else(|| element.property("vertex_index"))
+        .ok_or("missing vertex_indices property")?;
+    if !matches!(element.properties[property].kind, PropertyKind::List { .. }) {
+        return Err("vertex_indices is not a list property".to_string());
+    }
+
+    read_rows(element, body, Some(property), |_, polygon| {
+        for corner in 1..polygon.len().saturating_sub(1) {
+            indices.extend([polygon[0], polygon[corner], polygon[corner + 1]].map(|i| i as u32));
+        }
+    })
+}
+
+fn srgb_to_linear(value: f64) -> f32 {
+    let linear = match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    };
+
+    linear as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORED_QUAD: &str = "ply
+format ascii 1.0
+comment a quad with vertex colors
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+    #[test]
+    fn reads_vertex_indices_after_other_lists() {
+        let source = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar float texcoord
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+6 0.5 0.5 0.5 0.5 0.5 0.5 3 2 1 0
+";
+        let mesh = parse_ply(Path::new("triangle.ply"), source.as_bytes()).unwrap();
+
+        assert_eq!(mesh.indices, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_faces_without_vertex_indices() {
+        let source = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int corners
+end_header
+0 0 0
+1 0 0
+0 1 0
+3 0 1 2
+";
+        let result = parse_ply(Path::new("triangle.ply"), source.as_bytes());
+
+        assert!(matches!(result, Err(Error::InvalidData { .. })));
+    }
+
+    #[test]
+    fn reads_ascii_faces_and_colors() {
+        let mesh = parse_ply(Path::new("quad.ply"), COLORED_QUAD.as_bytes()).unwrap();
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.topology, Topology::Triangles);
+
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors[0], Vector4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(colors[3], Vector4::new(1.0, 1.0, 1.0, 1.0));
+        for normal in mesh.normals {
+            assert_eq!(normal, Vector3::unit_z());
+        }
+    }
+
+    #[test]
+    fn reads_binary_point_clouds() {
+        let header = "ply
+format binary_big_endian 1.0
+element vertex 2
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+end_header
+";
+        let mut data = header.as_bytes().to_vec();
+        for (position, normal) in [
+            ([1.0, 2.0, 3.0], [0.0, 0.0, 2.0]),
+            ([4.0, 5.0, 6.0], [0.0; 3]),
+        ] {
+            data.extend(position.iter().flat_map(|value: &f64| value.to_be_bytes()));
+            data.extend(normal.iter().flat_map(|value: &f32| value.to_be_bytes()));
+        }
+
+        let mesh = parse_ply(Path::new("points.ply"), &data).unwrap();
+
+        assert_eq!(mesh.topology, Topology::Points);
+        assert_eq!(
+            mesh.positions,
+            vec![Point3::new(1.0, 2.0, 3.0), Point3::new(4.0, 5.0, 6.0)]
+        );
+        // Zero length normals fall back to facing up instead of becoming NaN
+        assert_eq!(mesh.normals, vec![Vector3::unit_z(), Vector3::unit_y()]);
+        assert_eq!(mesh.indices, vec![0, 1]);
+        assert!(mesh.colors.is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_faces() {
+        let source = COLORED_QUAD.replace("4 0 1 2 3", "3 0 1 4");
+        let result = parse_ply(Path::new("quad.ply"), source.as_bytes());
+
+        assert!(matches!(result, Err(Error::InvalidData { .. })));
+    }
+
+    #[test]
+    fn rejects_truncated_binary_data() {
+        let data = b"ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n\0\0\0\0";
+        let result = parse_ply(Path::new("short.ply"), data);
+
+        assert!(matches!(result, Err(Error::InvalidData { .. })));
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use cgmath::{One, Point3, Quaternion, Vector2, Vector3, Vector4, Zero};
+
+use crate::material::{AlphaMode, CullMode, MaterialFactors, MaterialTextures, TextureTransforms};
+use crate::mesh::{generate, MeshVertex, Topology};
+use crate::model::Node;
+
+use super::{AssetKey, MaterialData, MeshData, ModelData, PrimitiveData, Vertices};
+
+/// Geometry of formats describing a single mesh without materials, like scans and CAD
+/// exports.
+pub struct ScanMesh {
+    pub positions: Vec<Point3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    /// Linear vertex colors, white when missing.
+    pub colors: Option<Vec<Vector4<f32>>>,
+    pub indices: Vec<u32>,
+    pub topology: Topology,
+}
+
+/// Builds a model with a single node drawing the mesh read from `path`. The material is a
+/// plain double sided dielectric, since scan and CAD data has no material and rarely a
+/// consistent winding.
+pub fn scan_model(path: &Path, mesh: ScanMesh) -> ModelData {
+    let vertex_count = mesh.positions.len();
+
+    // Without UVs the tangents only need to be perpendicular to the normals
+    let uvs = vec![Vector2::zero(); vertex_count];
+    let tangents = generate::tangents(&mesh.positions, &mesh.normals, &uvs, &mesh.indices);
+    let colors = mesh
+        .colors
+        .unwrap_or_else(|| vec![Vector4::new(1.0, 1.0, 1.0, 1.0); vertex_count]);
+
+    let vertices = mesh
+        .positions
+        .into_iter()
+        .zip(mesh.normals)
+        .zip(tangents)
+        .zip(colors)
+        .map(|(((position, normal), tangent), color)| {
+            let bitangent = generate::bitangent(normal, tangent);
+
+            MeshVertex::new(
+                position,
+                Vector2::zero(),
+                normal,
+                tangent.truncate(),
+                bitangent,
+            )
+            .with_color(color)
+        })
+        .collect();
+
+    let indices = mesh.indices.into_iter().map(|index| index as i32).collect();
+
+    let primitive = PrimitiveData {
+        mesh: MeshData {
+            key: AssetKey::path(path),
+            mesh_index: 0,
+            primitive_index: 0,
+            vertices: Vertices::Static(vertices),
+            indices,
+            topology: mesh.topology,
+            morph_targets: Vec::new(),
+        },
+        material_index: 0,
+    };
+
+    let textures = MaterialTextures {
+        base_color: None,
+        metallic_roughness: None,
+        normal: None,
+        occlusion: None,
+        emissive: None,
+        transforms: TextureTransforms::default(),
+    };
+    let factors = MaterialFactors {
+        metallic: 0.0,
+        roughness: 0.6,
+        ..MaterialFactors::default()
+    };
+    let material = MaterialData {
+        textures,
+        factors,
+        alpha_mode: AlphaMode::Opaque,
+        cull_mode: CullMode::Disabled,
+    };
+
+    let node = Node::new(
+        Some(0),
+        None,
+        Vec::new(),
+        Vector3::zero(),
+        Quaternion::one(),
+        Vector3::new(1.0, 1.0, 1.0),
+        Vec::new(),
+    );
+
+    ModelData {
+        sources: vec![path.to_path_buf()],
+        nodes: vec![node],
+        roots: vec![0],
+        meshes: vec![vec![primitive]],
+        images: Vec::new(),
+        materials: vec![material],
+        skins: Vec::new(),
+        animations: Vec::new(),
+    }
+}
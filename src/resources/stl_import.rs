@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use cgmath::Point3;
+
+use crate::mesh::{generate, Topology};
+
+use super::scan_import::{scan_model, ScanMesh};
+use super::{AssetReader, Error, ModelData};
+
+const BINARY_HEADER_SIZE: usize = 84;
+/// Normal, three vertices and a two byte attribute count.
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+impl AssetReader {
+    /// Reads a binary or ASCII STL file. The facet normals stored in the file are often
+    /// missing or wrong, so flat normals are computed from the winding of the triangles.
+    pub fn read_stl(&self, resource_path: &str) -> Result<ModelData, Error> {
+        let path = Path::new(resource_path);
+        let data = self.load_binary(path)?;
+
+        let positions = match binary_triangle_count(&data) {
+            Some(count) => parse_binary_stl(&data, count),
+            None => parse_ascii_stl(path, &String::from_utf8_lossy(&data))?,
+        };
+
+        let mesh = ScanMesh {
+            normals: generate::flat_normals(&positions),
+            indices: (0..positions.len() as u32).collect(),
+            positions,
+            colors: None,
+            topology: Topology::Triangles,
+        };
+
+        Ok(scan_model(path, mesh))
+    }
+}
+
+/// Binary files are recognized by being large enough for the triangle count in their header
+/// and not being text, as some of them also start with `solid` like ASCII files and others
+/// are padded past their triangles.
+fn binary_triangle_count(data: &[u8]) -> Option<usize> {
+    let count = data.get(80..BINARY_HEADER_SIZE)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+
+    let is_text = data
+        .iter()
+        .all(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace());
+
+    match data.len() >= BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE && !is_text {
+        true => Some(count),
+        false => None,
+    }
+}
+
+fn parse_binary_stl(data: &[u8], count: usize) -> Vec<Point3<f32>> {
+    let read_f32 = |offset: usize| {
+        f32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+
+    (0..count)
+        .flat_map(|triangle| {
+            // Skip the facet normal
+            let start = BINARY_HEADER_SIZE + triangle * BINARY_TRIANGLE_SIZE + 12;
+
+            (0..3).map(move |vertex| {
+                let offset = start + vertex * 12;
+                Point3::new(read_f32(offset), read_f32(offset + 4), read_f32(offset + 8))
+            })
+        })
+        .collect()
+}
+
+fn parse_ascii_stl(path: &Path, source: &str) -> Result<Vec<Point3<f32>>, Error> {
+    if !source.trim_start().starts_with("solid") {
+        return Err(Error::InvalidData {
+            path: path.to_path_buf(),
+            message: "neither a binary nor an ASCII STL file".to_string(),
+        });
+    }
+
+    let mut positions = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("vertex") {
+            continue;
+        }
+
+        let mut coordinates = [0.0; 3];
+        for coordinate in coordinates.iter_mut() {
+            let token = tokens.next().unwrap_or("");
+            *coordinate = token.parse().map_err(|_| Error::Parse {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message: format!("invalid coordinate {:?}", token),
+            })?;
+        }
+
+        positions.push(Point3::from(coordinates));
+    }
+
+    if positions.is_empty() {
+        return Err(Error::InvalidData {
+            path: path.to_path_buf(),
+            message: "no facets found".to_string(),
+        });
+    }
+    if !positions.len().is_multiple_of(3) {
+        return Err(Error::InvalidData {
+            path: path.to_path_buf(),
+            message: format!("{} vertices do not form whole triangles", positions.len()),
+        });
+    }
+
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{MemoryMount, Vertices};
+
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    /// Binary STL whose header starts with `solid`, like many exporters write it.
+    fn binary_stl(triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut data = vec![0; 80];
+        data[..5].copy_from_slice(b"solid");
+        data.extend((triangles.len() as u32).to_le_bytes());
+
+        for triangle in triangles {
+            data.extend([0.0f32; 3].iter().flat_map(|value| value.to_le_bytes()));
+            data.extend(
+                triangle
+                    .iter()
+                    .flatten()
+                    .flat_map(|value| value.to_le_bytes()),
+            );
+            data.extend([0, 0]);
+        }
+
+        data
+    }
+
+    #[test]
+    fn reads_binary_files_starting_with_solid() {
+        let data = binary_stl(&[TRIANGLE]);
+
+        assert_eq!(binary_triangle_count(&data), Some(1));
+        assert_eq!(
+            parse_binary_stl(&data, 1),
+            TRIANGLE.map(Point3::from).to_vec()
+        );
+    }
+
+    #[test]
+    fn reads_padded_binary_files() {
+        let mut data = binary_stl(&[TRIANGLE]);
+        data.extend([0; 16]);
+
+        assert_eq!(binary_triangle_count(&data), Some(1));
+    }
+
+    #[test]
+    fn rejects_ascii_files_without_facets() {
+        let source = "solid empty\nendsolid empty\n";
+
+        assert!(matches!(
+            parse_ascii_stl(Path::new("empty.stl"), source),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn reads_ascii_files() {
+        let source = "solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid triangle
+";
+
+        assert_eq!(binary_triangle_count(source.as_bytes()), None);
+        assert_eq!(
+            parse_ascii_stl(Path::new("triangle.stl"), source).unwrap(),
+            TRIANGLE.map(Point3::from).to_vec()
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_ascii_triangles() {
+        let source = "solid broken\nvertex 0 0 0\nvertex 1 0 0\nendsolid broken\n";
+
+        assert!(matches!(
+            parse_ascii_stl(Path::new("broken.stl"), source),
+            Err(Error::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn computes_flat_normals_from_the_winding() {
+        let reader = AssetReader::new()
+            .with_mount(MemoryMount::new().with_file("triangle.stl", binary_stl(&[TRIANGLE])));
+
+        let model = reader.read_stl("triangle.stl").unwrap();
+
+        let mesh = &model.meshes[0][0].mesh;
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        let Vertices::Static(vertices) = &mesh.vertices else {
+            panic!("STL meshes are static");
+        };
+        for vertex in vertices {
+            assert_eq!(vertex.normal(), cgmath::Vector3::unit_z());
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// Source of asset files, addressed by paths relative to the mount. Mounts are shared with
+/// the threads of the background loader.
+pub trait Mount: Send + Sync {
+    fn contains(&self, path: &Path) -> bool;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Describes where the mount reads from, for errors listing the searched mounts.
+    fn describe(&self) -> String;
+
+    /// When the file was last modified, used to reload changed assets. Mounts whose files
+    /// cannot change return `None`.
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// Loose files below a directory on disk.
+pub struct DirectoryMount {
+    root: PathBuf,
+}
+
+impl DirectoryMount {
+    pub fn new<P: Into<PathBuf>>(root: P) -> DirectoryMount {
+        DirectoryMount { root: root.into() }
+    }
+}
+
+impl Mount for DirectoryMount {
+    fn contains(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn describe(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(self.root.join(path))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+/// Files of an uncompressed tar archive, so a build can ship its assets as a single pack
+/// created with `tar -cf assets.tar assets`. The archive is read into memory when mounted.
+pub struct PackMount {
+    path: PathBuf,
+    data: Vec<u8>,
+    /// Byte range of every regular file in the archive.
+    entries: HashMap<PathBuf, (usize, usize)>,
+}
+
+impl PackMount {
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<PackMount> {
+        let path = path.into();
+        let data = fs::read(&path)?;
+        let entries = parse_tar(&data)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))?;
+
+        Ok(PackMount {
+            path,
+            data,
+            entries,
+        })
+    }
+}
+
+impl Mount for PackMount {
+    fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.entries.get(path) {
+            Some((start, end)) => Ok(self.data[*start..*end].to_vec()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Files held in memory, for assets compiled into the executable or generated at runtime.
+#[derive(Default)]
+pub struct MemoryMount {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryMount {
+    pub fn new() -> MemoryMount {
+        MemoryMount::default()
+    }
+
+    pub fn with_file<P: AsRef<Path>, D: Into<Vec<u8>>>(mut self, path: P, data: D) -> MemoryMount {
+        self.insert(path, data);
+        self
+    }
+
+    pub fn insert<P: AsRef<Path>, D: Into<Vec<u8>>>(&mut self, path: P, data: D) {
+        self.files.insert(normalize(path.as_ref()), data.into());
+    }
+}
+
+impl Mount for MemoryMount {
+    fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.files.get(path) {
+            Some(data) => Ok(data.clone()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        "<memory>".to_string()
+    }
+}
+
+/// Removes `.` components and resolves `..` against the preceding components, so paths like
+/// `models/../textures/a.png` match the entries of packs and memory mounts. Leading `..`
+/// components are kept.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut components: Vec<Component> = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match components.last() {
+                Some(Component::Normal(_)) => {
+                    components.pop();
+                }
+                _ => components.push(component),
+            },
+            component => components.push(component),
+        }
+    }
+
+    components.iter().collect()
+}
+
+/// Indexes the regular files of a ustar or GNU tar archive.
+fn parse_tar(data: &[u8]) -> Result<HashMap<PathBuf, (usize, usize)>, String> {
+    let mut entries = HashMap::new();
+    let mut long_name: Option<String> = None;
+    let mut offset = 0;
+
+    while offset + TAR_BLOCK_SIZE <= data.len() {
+        let header = &data[offset..offset + TAR_BLOCK_SIZE];
+
+        // The archive ends with zeroed blocks
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        let size = parse_octal(&header[124..136])
+            .ok_or(format!("invalid entry size at offset {}", offset))?;
+        let start = offset + TAR_BLOCK_SIZE;
+        let end = start + size;
+        if end > data.len() {
+            return Err(format!("entry at offset {} is truncated", offset));
+        }
+
+        match header[156] {
+            // GNU long names are stored in an entry of their own before the file
+            b'L' => long_name = Some(tar_string(&data[start..end])),
+            b'0' | 0 => {
+                let name = match long_name.take() {
+                    Some(name) => name,
+                    None => {
+                        let name = tar_string(&header[0..100]);
+                        let prefix = match &header[257..262] == b"ustar" {
+                            true => tar_string(&header[345..500]),
+                            false => String::new(),
+                        };
+
+                        match prefix.is_empty() {
+                            true => name,
+                            false => format!("{}/{}", prefix, name),
+                        }
+                    }
+                };
+
+                entries.insert(normalize(Path::new(&name)), (start, end));
+            }
+            // Directories, links and other entries carry no file data to load
+            _ => long_name = None,
+        }
+
+        offset = start + size.next_multiple_of(TAR_BLOCK_SIZE);
+    }
+
+    Ok(entries)
+}
+
+/// Reads a NUL terminated string field.
+fn tar_string(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Reads a numeric field stored as octal digits padded with spaces or NULs.
+fn parse_octal(field: &[u8]) -> Option<usize> {
+    let digits = tar_string(field);
+    let digits = digits.trim_matches(' ');
+
+    match digits.is_empty() {
+        true => Some(0),
+        false => usize::from_str_radix(digits, 8).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::AssetReader;
+
+    /// Builds a ustar header followed by the data padded to whole blocks. Checksums are not
+    /// verified by the parser, so they are left empty.
+    fn tar_entry(name: &str, prefix: &str, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut header = vec![0; TAR_BLOCK_SIZE];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}", data.len());
+        header[124..135].copy_from_slice(size.as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        let mut entry = header;
+        entry.extend_from_slice(data);
+        entry.resize(entry.len().next_multiple_of(TAR_BLOCK_SIZE), 0);
+        entry
+    }
+
+    fn tar(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut archive = entries.concat();
+        archive.extend_from_slice(&[0; 2 * TAR_BLOCK_SIZE]);
+        archive
+    }
+
+    fn read<'a>(
+        archive: &'a [u8],
+        entries: &HashMap<PathBuf, (usize, usize)>,
+        path: &str,
+    ) -> &'a [u8] {
+        let (start, end) = entries[Path::new(path)];
+        &archive[start..end]
+    }
+
+    #[test]
+    fn joins_the_ustar_prefix_and_name() {
+        let archive = tar(&[tar_entry("a.png", "assets/textures", b'0', b"pixels")]);
+        let entries = parse_tar(&archive).unwrap();
+
+        assert_eq!(read(&archive, &entries, "assets/textures/a.png"), b"pixels");
+    }
+
+    #[test]
+    fn reads_gnu_long_names() {
+        let name = format!("assets/{}.obj", "long".repeat(40));
+        let archive = tar(&[
+            tar_entry("././@LongLink", "", b'L', format!("{}\0", name).as_bytes()),
+            tar_entry(&name[..100], "", b'0', b"model"),
+            tar_entry("short.txt", "", b'0', b"text"),
+        ]);
+        let entries = parse_tar(&archive).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(read(&archive, &entries, &name), b"model");
+        assert_eq!(read(&archive, &entries, "short.txt"), b"text");
+    }
+
+    #[test]
+    fn skips_directories() {
+        let archive = tar(&[
+            tar_entry("assets/", "", b'5', b""),
+            tar_entry("assets/a.txt", "", b'0', b"a"),
+        ]);
+        let entries = parse_tar(&archive).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(read(&archive, &entries, "assets/a.txt"), b"a");
+    }
+
+    #[test]
+    fn rejects_truncated_entries() {
+        let mut archive = tar_entry("a.bin", "", b'0', &[1; 1000]);
+        archive.truncate(TAR_BLOCK_SIZE + 600);
+
+        assert!(parse_tar(&archive).is_err());
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize(Path::new("a/../b")), PathBuf::from("b"));
+        assert_eq!(normalize(Path::new("./a")), PathBuf::from("a"));
+        assert_eq!(normalize(Path::new("../a/./b")), PathBuf::from("../a/b"));
+        assert_eq!(normalize(Path::new("a/../../b")), PathBuf::from("../b"));
+    }
+
+    #[test]
+    fn earlier_mounts_shadow_later_ones() {
+        let reader = AssetReader::new()
+            .with_mount(MemoryMount::new().with_file("shared.txt", "first"))
+            .with_mount(
+                MemoryMount::new()
+                    .with_file("shared.txt", "second")
+                    .with_file("only_second.txt", "second"),
+            );
+
+        let read = |path: &str| reader.load_binary(Path::new(path)).unwrap();
+        assert_eq!(read("shared.txt"), b"first");
+        assert_eq!(read("./models/../shared.txt"), b"first");
+        assert_eq!(read("only_second.txt"), b"second");
+        assert!(reader.load_binary(Path::new("missing.txt")).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::AssetReader;
+
+/// Polls the modification times of asset files, so changed assets can be reloaded while the
+/// renderer runs. Files in mounts that cannot change, like packs, are never reported.
+pub struct AssetWatcher {
+    reader: AssetReader,
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl AssetWatcher {
+    pub fn new(reader: &AssetReader, interval: Duration) -> AssetWatcher {
+        AssetWatcher {
+            reader: reader.clone(),
+            files: HashMap::new(),
+            interval,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Starts watching a file, changes are reported relative to its current state. Watching
+    /// a file again does nothing.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+
+        if !self.files.contains_key(path) {
+            let modified = self.reader.modified(path);
+            self.files.insert(path.to_path_buf(), modified);
+        }
+    }
+
+    /// Returns the watched files modified since the last poll. The files are checked at most
+    /// once per interval, nothing is returned in between.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+
+        for (path, modified) in self.files.iter_mut() {
+            let current = self.reader.modified(path);
+
+            if current != *modified {
+                *modified = current;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
@@ -49,6 +49,7 @@ impl Shader {
             unsafe {
                 gl.GetShaderInfoLog(shader_id, 512, &mut log_len, info_log.as_mut_ptr().cast());
                 info_log.set_len(log_len.try_into().unwrap());
+                gl.DeleteShader(shader_id);
             }
 
             return Err(format!(
@@ -1,56 +1,110 @@
-use crate::{shader::Shader, uniform::Uniform};
+use std::cell::Cell;
+
+use crate::{resources::ResourceLoader, shader::Shader, uniform::Uniform};
 
 pub struct Program {
     gl: gl::Gl,
-    id: gl::types::GLuint,
+    /// Replaced when the program is relinked, materials keep referring to the same `Program`.
+    id: Cell<gl::types::GLuint>,
 }
 
 impl Program {
     pub fn from_shaders(gl: &gl::Gl, shaders: &[Shader]) -> Result<Program, String> {
-        let program_id = unsafe { gl.CreateProgram() };
+        let program_id = link(gl, shaders)?;
 
-        for shader in shaders {
-            unsafe { gl.AttachShader(program_id, shader.id()) };
-        }
+        println!("Shader program was created successfully");
+        Ok(Program {
+            gl: gl.clone(),
+            id: Cell::new(program_id),
+        })
+    }
 
-        unsafe { gl.LinkProgram(program_id) };
+    /// Compiles and links a vertex and a fragment shader file.
+    pub fn from_files(
+        gl: &gl::Gl,
+        res: &ResourceLoader,
+        vertex_path: &str,
+        fragment_path: &str,
+    ) -> Result<Program, String> {
+        let vertex_shader = Shader::from_vertex_source(gl, res, vertex_path)?;
+        let fragment_shader = Shader::from_fragment_source(gl, res, fragment_path)?;
 
-        for shader in shaders {
-            unsafe { gl.DetachShader(program_id, shader.id()) };
-        }
+        Program::from_shaders(gl, &[vertex_shader, fragment_shader])
+    }
 
-        let mut success: gl::types::GLint = 1;
-        unsafe { gl.GetShaderiv(program_id, gl::LINK_STATUS, &mut success) };
+    /// Compiles the shader files again and relinks the program, keeping the current one when
+    /// compiling or linking fails.
+    pub fn reload_files(
+        &self,
+        res: &ResourceLoader,
+        vertex_path: &str,
+        fragment_path: &str,
+    ) -> Result<(), String> {
+        let vertex_shader = Shader::from_vertex_source(&self.gl, res, vertex_path)?;
+        let fragment_shader = Shader::from_fragment_source(&self.gl, res, fragment_path)?;
 
-        if success == 0 {
-            let mut log_len = 0_i32;
-            let mut info_log: Vec<u8> = Vec::with_capacity(1024);
+        self.relink(&[vertex_shader, fragment_shader])
+    }
 
-            unsafe {
-                gl.GetProgramInfoLog(program_id, 512, &mut log_len, info_log.as_mut_ptr().cast());
-                info_log.set_len(log_len.try_into().unwrap());
-            }
+    /// Links the shaders into a new program replacing the current one, which is kept when
+    /// linking fails.
+    pub fn relink(&self, shaders: &[Shader]) -> Result<(), String> {
+        let program_id = link(&self.gl, shaders)?;
 
-            return Err(format!(
-                "Error: Program linking failed: {}",
-                String::from_utf8_lossy(&info_log)
-            ));
-        };
+        unsafe { self.gl.DeleteProgram(self.id.replace(program_id)) };
 
-        println!("Shader program was created successfully");
-        Ok(Program {
-            gl: gl.clone(),
-            id: program_id,
-        })
+        println!("Shader program was relinked successfully");
+        Ok(())
     }
 
     pub fn use_program(&self) {
-        unsafe { self.gl.UseProgram(self.id) };
+        unsafe { self.gl.UseProgram(self.id.get()) };
     }
 
     pub fn set_uniforms(&self, uniforms: &Vec<Box<dyn Uniform>>) {
         for uniform in uniforms {
-            uniform.set(&self.gl, self.id);
+            uniform.set(&self.gl, self.id.get());
         }
     }
 }
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteProgram(self.id.get()) };
+    }
+}
+
+fn link(gl: &gl::Gl, shaders: &[Shader]) -> Result<gl::types::GLuint, String> {
+    let program_id = unsafe { gl.CreateProgram() };
+
+    for shader in shaders {
+        unsafe { gl.AttachShader(program_id, shader.id()) };
+    }
+
+    unsafe { gl.LinkProgram(program_id) };
+
+    for shader in shaders {
+        unsafe { gl.DetachShader(program_id, shader.id()) };
+    }
+
+    let mut success: gl::types::GLint = 1;
+    unsafe { gl.GetProgramiv(program_id, gl::LINK_STATUS, &mut success) };
+
+    if success == 0 {
+        let mut log_len = 0_i32;
+        let mut info_log: Vec<u8> = Vec::with_capacity(1024);
+
+        unsafe {
+            gl.GetProgramInfoLog(program_id, 512, &mut log_len, info_log.as_mut_ptr().cast());
+            info_log.set_len(log_len.try_into().unwrap());
+            gl.DeleteProgram(program_id);
+        }
+
+        return Err(format!(
+            "Error: Program linking failed: {}",
+            String::from_utf8_lossy(&info_log)
+        ));
+    };
+
+    Ok(program_id)
+}
@@ -1,23 +1,72 @@
+use std::path::Path;
+
 use cgmath::Point3;
 
 use crate::{
     camera::Camera,
     mesh::{Mesh, Vertex},
     resources::ResourceLoader,
-    shader::{Program, Shader},
-    texture::CubeMap,
+    shader::Program,
+    texture::{ColorSpace, CubeMap},
     uniform::UniformMat4f,
 };
 
+const VERTEX_SHADER: &str = "assets/shaders/skybox.vert";
+const FRAGMENT_SHADER: &str = "assets/shaders/skybox.frag";
+
+/// Images a skybox is built from. Radiance and OpenEXR images give an HDR skybox.
+pub enum SkyboxSource {
+    /// Face images in the order +X, -X, +Y, -Y, +Z, -Z.
+    Faces([String; 6]),
+    /// Equirectangular panorama, like most HDRI assets, converted to faces of `face_size`
+    /// pixels.
+    Panorama { path: String, face_size: u32 },
+}
+
+impl SkyboxSource {
+    fn files(&self) -> Vec<&str> {
+        match self {
+            SkyboxSource::Faces(faces) => faces.iter().map(String::as_str).collect(),
+            SkyboxSource::Panorama { path, .. } => vec![path.as_str()],
+        }
+    }
+
+    fn load(&self, gl: &gl::Gl, res: &ResourceLoader) -> Result<CubeMap, String> {
+        match self {
+            SkyboxSource::Faces(faces) => CubeMap::load(
+                gl,
+                res,
+                faces.each_ref().map(String::as_str),
+                ColorSpace::Srgb,
+            ),
+            SkyboxSource::Panorama { path, face_size } => {
+                CubeMap::load_panorama(gl, res, path, *face_size, ColorSpace::Srgb)
+            }
+        }
+    }
+
+    fn reload(&self, res: &ResourceLoader, cube_map: &CubeMap) -> Result<(), String> {
+        match self {
+            SkyboxSource::Faces(faces) => {
+                cube_map.reload(res, faces.each_ref().map(String::as_str))
+            }
+            SkyboxSource::Panorama { path, face_size } => {
+                cube_map.reload_panorama(res, path, *face_size)
+            }
+        }
+    }
+}
+
 pub struct Skybox {
     gl: gl::Gl,
     mesh: Mesh,
+    source: SkyboxSource,
     cube_map: CubeMap,
     program: Program,
 }
 
 impl Skybox {
-    pub fn new(gl: &gl::Gl, res: &ResourceLoader) -> Skybox {
+    pub fn new(gl: &gl::Gl, res: &ResourceLoader, source: SkyboxSource) -> Result<Skybox, String> {
         let vertices = vec![
             // Front
             SkyboxVertex::new((-1.0, -1.0, 1.0)),
@@ -61,32 +110,50 @@ impl Skybox {
         ];
         let mesh = Mesh::create(gl, vertices, indices);
 
-        let images_paths = [
-            "assets/skybox/sky/right.jpg",
-            "assets/skybox/sky/left.jpg",
-            "assets/skybox/sky/top.jpg",
-            "assets/skybox/sky/bottom.jpg",
-            "assets/skybox/sky/front.jpg",
-            "assets/skybox/sky/back.jpg",
-        ];
-        let cube_map = CubeMap::load(gl, res, images_paths).unwrap();
-
-        let vertex_shader =
-            Shader::from_vertex_source(gl, res, "assets/shaders/skybox.vert").unwrap();
-        let fragment_shader =
-            Shader::from_fragment_source(gl, res, "assets/shaders/skybox.frag").unwrap();
-        let program = Program::from_shaders(gl, &[vertex_shader, fragment_shader]).unwrap();
+        let cube_map = source.load(gl, res)?;
+        let program = Program::from_files(gl, res, VERTEX_SHADER, FRAGMENT_SHADER)?;
 
-        Skybox {
+        Ok(Skybox {
             gl: gl.clone(),
             mesh,
+            source,
             cube_map,
             program,
+        })
+    }
+
+    /// The shader and image files of the skybox.
+    pub fn files(&self) -> Vec<&str> {
+        [VERTEX_SHADER, FRAGMENT_SHADER]
+            .into_iter()
+            .chain(self.source.files())
+            .collect()
+    }
+
+    /// Reloads the shaders or the images when `path` is one of the skybox files, keeping the
+    /// current ones on failure. Returns whether the file belongs to the skybox.
+    pub fn reload(&self, res: &ResourceLoader, path: &Path) -> Result<bool, String> {
+        if path == Path::new(VERTEX_SHADER) || path == Path::new(FRAGMENT_SHADER) {
+            self.program
+                .reload_files(res, VERTEX_SHADER, FRAGMENT_SHADER)?;
+        } else if self
+            .source
+            .files()
+            .iter()
+            .any(|file| path == Path::new(file))
+        {
+            self.source.reload(res, &self.cube_map)?;
+        } else {
+            return Ok(false);
         }
+
+        Ok(true)
     }
 
     pub fn draw(&self, camera: &Camera) {
         unsafe {
+            // The cube is seen from the inside, so its faces must not be culled
+            self.gl.Disable(gl::CULL_FACE);
             self.gl.DepthMask(gl::FALSE);
             self.program.use_program();
 
@@ -124,4 +191,8 @@ impl Vertex for SkyboxVertex {
             gl.EnableVertexAttribArray(0);
         }
     }
+
+    fn position(&self) -> Point3<f32> {
+        self.position
+    }
 }
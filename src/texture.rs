@@ -1,13 +1,211 @@
+use std::f32::consts::PI;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::rc::Rc;
+
+use cgmath::{InnerSpace, Vector3};
+
 use crate::resources::ResourceLoader;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Wrap {
+    pub fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+/// Encoding of the color values stored in a texture. sRGB textures are converted to linear
+/// values when sampled, so lighting is computed in linear space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+/// Internal formats of textures holding HDR data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FloatFormat {
+    Rgb16F,
+    Rgba16F,
+    Rgba32F,
+}
+
+impl FloatFormat {
+    /// Largest value a half float can hold.
+    const HALF_MAX: f32 = 65504.0;
+
+    /// Picks half floats, which are enough for most HDR images, unless a value is out of
+    /// their range, like the sun in a captured environment.
+    pub fn fitting(pixels: &[f32], alpha: bool) -> FloatFormat {
+        match (
+            pixels.iter().any(|value| value.abs() > Self::HALF_MAX),
+            alpha,
+        ) {
+            (true, _) => FloatFormat::Rgba32F,
+            (false, true) => FloatFormat::Rgba16F,
+            (false, false) => FloatFormat::Rgb16F,
+        }
+    }
+
+    pub fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            FloatFormat::Rgb16F => gl::RGB16F,
+            FloatFormat::Rgba16F => gl::RGBA16F,
+            FloatFormat::Rgba32F => gl::RGBA32F,
+        }
+    }
+}
+
+/// Describes how a texture is sampled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerDesc {
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Filter used between mipmap levels, `None` disables mipmaps.
+    pub mipmap_filter: Option<Filter>,
+    /// Maximum anisotropy, `1.0` disables anisotropic filtering.
+    pub anisotropy: f32,
+}
+
+// The anisotropy is never NaN, so the descriptors can key the texture cache
+impl Eq for SamplerDesc {}
+
+impl Hash for SamplerDesc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.wrap_s.hash(state);
+        self.wrap_t.hash(state);
+        self.min_filter.hash(state);
+        self.mag_filter.hash(state);
+        self.mipmap_filter.hash(state);
+        self.anisotropy.to_bits().hash(state);
+    }
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            anisotropy: 1.0,
+        }
+    }
+}
+
+impl SamplerDesc {
+    pub fn min_filter_to_gl(&self) -> gl::types::GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    pub fn mag_filter_to_gl(&self) -> gl::types::GLenum {
+        match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// Pixels of a texture. Floating point images, like Radiance and OpenEXR files, keep their
+/// range and are uploaded in the float format chosen when decoding them.
+pub enum TextureImage {
+    Ldr(image::RgbaImage),
+    Hdr(image::Rgba32FImage, FloatFormat),
+}
+
+impl TextureImage {
+    pub fn from_dynamic(img: image::DynamicImage) -> TextureImage {
+        match img {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => {
+                let alpha = img.color().has_alpha();
+                let img = img.into_rgba32f();
+                let format = FloatFormat::fitting(img.as_raw(), alpha);
+
+                TextureImage::Hdr(img, format)
+            }
+            img => TextureImage::Ldr(img.into_rgba8()),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        match self {
+            TextureImage::Ldr(img) => img.width(),
+            TextureImage::Hdr(img, _) => img.width(),
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            TextureImage::Ldr(img) => img.height(),
+            TextureImage::Hdr(img, _) => img.height(),
+        }
+    }
+
+    /// Size of the pixel data in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            TextureImage::Ldr(img) => std::mem::size_of_val(img.as_raw().as_slice()),
+            TextureImage::Hdr(img, _) => std::mem::size_of_val(img.as_raw().as_slice()),
+        }
+    }
+}
+
+/// Decodes an encoded image, using the mime type when given and guessing the format from the
+/// data otherwise.
+pub fn decode_image(data: &[u8], mime_type: Option<&str>) -> Result<TextureImage, String> {
+    let format = match mime_type {
+        Some(mime_type) => image::ImageFormat::from_mime_type(mime_type)
+            .ok_or(format!("Unsupported image mime type {}", mime_type))?,
+        None => image::guess_format(data)
+            .map_err(|e| format!("Error guessing image format: {:?}", e))?,
+    };
+
+    let img = image::load_from_memory_with_format(data, format)
+        .map_err(|e| format!("Error decoding image: {:?}", e))?;
+
+    Ok(TextureImage::from_dynamic(img))
+}
+
 pub struct Texture {
     gl: gl::Gl,
     id: gl::types::GLuint,
+    sampler: SamplerDesc,
+    color_space: ColorSpace,
 }
 
 impl Texture {
-    fn new(gl: &gl::Gl, img: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>) -> Texture {
+    pub fn new(
+        gl: &gl::Gl,
+        img: TextureImage,
+        sampler: &SamplerDesc,
+        color_space: ColorSpace,
+    ) -> Texture {
         let mut id = 0;
 
         unsafe {
@@ -15,47 +213,124 @@ impl Texture {
             gl.BindTexture(gl::TEXTURE_2D, id);
 
             let target = gl::TEXTURE_2D;
-            gl.TexParameteri(target, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl.TexParameteri(target, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl.TexParameteri(target, gl::TEXTURE_WRAP_S, sampler.wrap_s.to_gl() as i32);
+            gl.TexParameteri(target, gl::TEXTURE_WRAP_T, sampler.wrap_t.to_gl() as i32);
             gl.TexParameteri(
                 target,
                 gl::TEXTURE_MIN_FILTER,
-                gl::LINEAR_MIPMAP_LINEAR as i32,
+                sampler.min_filter_to_gl() as i32,
             );
-            gl.TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-            gl.TexImage2D(
+            gl.TexParameteri(
+                target,
+                gl::TEXTURE_MAG_FILTER,
+                sampler.mag_filter_to_gl() as i32,
+            );
+
+            if sampler.anisotropy > 1.0 {
+                let mut max_anisotropy = 1.0;
+                gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+                gl.TexParameterf(
+                    target,
+                    gl::TEXTURE_MAX_ANISOTROPY,
+                    sampler.anisotropy.min(max_anisotropy),
+                );
+            }
+        }
+
+        let texture = Texture {
+            gl: gl.clone(),
+            id,
+            sampler: *sampler,
+            color_space,
+        };
+        texture.replace_image(&img);
+
+        texture
+    }
+
+    /// Replaces the pixels of the texture, keeping its sampler and color space. The image
+    /// may have a different size and format.
+    pub fn replace_image(&self, img: &TextureImage) {
+        // HDR images hold linear values, so their color space needs no conversion
+        let (internal_format, pixel_type, pixels) = match img {
+            TextureImage::Ldr(pixels) => {
+                let internal_format = match self.color_space {
+                    ColorSpace::Srgb => gl::SRGB8_ALPHA8,
+                    ColorSpace::Linear => gl::RGBA8,
+                };
+                (internal_format, gl::UNSIGNED_BYTE, pixels.as_ptr().cast())
+            }
+            TextureImage::Hdr(pixels, format) => {
+                (format.to_gl(), gl::FLOAT, pixels.as_ptr().cast())
+            }
+        };
+
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+            self.gl.TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGBA as i32,
+                internal_format as i32,
                 img.width() as i32,
                 img.height() as i32,
                 0,
                 gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                img.as_ptr().cast(),
+                pixel_type,
+                pixels,
             );
-            gl.GenerateMipmap(gl::TEXTURE_2D);
+
+            if self.sampler.mipmap_filter.is_some() {
+                self.gl.GenerateMipmap(gl::TEXTURE_2D);
+            }
         }
+    }
+
+    pub fn from_color(gl: &gl::Gl, color: [u8; 4], color_space: ColorSpace) -> Texture {
+        let img = image::ImageBuffer::from_pixel(1, 1, image::Rgba(color));
 
-        Texture { gl: gl.clone(), id }
+        Texture::new(
+            gl,
+            TextureImage::Ldr(img),
+            &SamplerDesc::default(),
+            color_space,
+        )
     }
 
-    pub fn load(gl: &gl::Gl, res: &ResourceLoader, path: &str) -> Result<Texture, String> {
-        let img = res
-            .load_image(path)
-            .map_err(|e| format!("Error loading image {}: {:?}", path, e))?;
+    pub fn sampler(&self) -> &SamplerDesc {
+        &self.sampler
+    }
 
-        Ok(Texture::new(gl, img))
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
     }
 
-    // pub fn from_binary_data(gl: &gl::Gl, data: &[u8]) -> Result<Texture, String> {
-    //     let mut reader = image::io::Reader::new(std::io::Cursor::new(data));
-    //     reader.set_format(image::ImageFormat::Png);
-    //     // reader.no_limits();
-    //     let img = reader.decode().map_err(|_| "Hey")?.flipv().to_rgba8();
+    /// Reads the base level back from the GPU. sRGB textures return their encoded values.
+    pub fn read_image(&self) -> image::ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+        let (mut width, mut height) = (0, 0);
+
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+            self.gl
+                .GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut width);
+            self.gl
+                .GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut height);
+        }
 
-    //     Ok(Texture::new(gl, img))
-    // }
+        let mut img: image::ImageBuffer<image::Rgba<u8>, Vec<u8>> =
+            image::ImageBuffer::new(width as u32, height as u32);
+
+        unsafe {
+            self.gl.GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                img.as_mut_ptr().cast(),
+            );
+        }
+
+        img
+    }
 
     pub fn bind(&self, slot: gl::types::GLenum) {
         unsafe {
@@ -71,12 +346,74 @@ impl Drop for Texture {
     }
 }
 
+/// 1x1 textures bound in place of the maps a material does not provide, chosen so that
+/// sampling them leaves the material factors unchanged.
+pub struct DefaultTextures {
+    /// Used for missing base color, metallic-roughness, occlusion and emissive maps. Emissive
+    /// also uses white, so a material with only an emissive factor still glows.
+    pub white: Rc<Texture>,
+    /// Tangent space normal pointing straight out of the surface.
+    pub flat_normal: Rc<Texture>,
+}
+
+impl DefaultTextures {
+    pub fn new(gl: &gl::Gl) -> DefaultTextures {
+        DefaultTextures {
+            // White is the same in both color spaces, so it can stand in for any map
+            white: Rc::new(Texture::from_color(
+                gl,
+                [255, 255, 255, 255],
+                ColorSpace::Linear,
+            )),
+            flat_normal: Rc::new(Texture::from_color(
+                gl,
+                [128, 128, 255, 255],
+                ColorSpace::Linear,
+            )),
+        }
+    }
+}
+
+/// Faces of a cube map in the order +X, -X, +Y, -Y, +Z, -Z. HDR faces are uploaded as half
+/// floats unless their values are out of range.
+pub enum CubeFaces {
+    Ldr([image::RgbImage; 6]),
+    Hdr([image::Rgb32FImage; 6]),
+}
+
+impl CubeFaces {
+    /// Projects an equirectangular panorama onto the faces, each `face_size` pixels wide.
+    /// Faces are sampled bilinearly, so they can be larger or smaller than the panorama.
+    pub fn from_panorama(panorama: PanoramaImage, face_size: u32) -> Result<CubeFaces, String> {
+        match panorama {
+            PanoramaImage::Ldr(image) => {
+                let image = image::DynamicImage::ImageRgb8(image).into_rgb32f();
+                let faces = project_panorama(&image, face_size)?;
+
+                Ok(CubeFaces::Ldr(faces.map(|face| {
+                    image::DynamicImage::ImageRgb32F(face).into_rgb8()
+                })))
+            }
+            PanoramaImage::Hdr(image) => Ok(CubeFaces::Hdr(project_panorama(&image, face_size)?)),
+        }
+    }
+}
+
+/// Equirectangular panorama, with the longitude along its width and the latitude along its
+/// height.
+pub enum PanoramaImage {
+    Ldr(image::RgbImage),
+    Hdr(image::Rgb32FImage),
+}
+
 pub struct CubeMap {
+    gl: gl::Gl,
     pub id: gl::types::GLuint,
+    color_space: ColorSpace,
 }
 
 impl CubeMap {
-    pub fn new(gl: &gl::Gl, images: [image::ImageBuffer<image::Rgb<u8>, Vec<u8>>; 6]) -> CubeMap {
+    pub fn new(gl: &gl::Gl, faces: CubeFaces, color_space: ColorSpace) -> CubeMap {
         let mut id = 0;
         unsafe {
             gl.GenTextures(1, &mut id);
@@ -90,37 +427,277 @@ impl CubeMap {
             gl.TexParameteri(target, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
         };
 
-        for (index, image) in images.iter().enumerate() {
-            unsafe {
-                gl.TexImage2D(
-                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + index as u32,
-                    0,
-                    gl::RGB as i32,
-                    image.width() as i32,
-                    image.height() as i32,
-                    0,
-                    gl::RGB,
-                    gl::UNSIGNED_BYTE,
-                    image.as_ptr().cast(),
-                );
-            }
-        }
+        let cube_map = CubeMap {
+            gl: gl.clone(),
+            id,
+            color_space,
+        };
+        cube_map.replace_faces(&faces);
 
-        CubeMap { id }
+        cube_map
     }
 
     pub fn load(
         gl: &gl::Gl,
         res: &ResourceLoader,
         images_paths: [&str; 6],
+        color_space: ColorSpace,
     ) -> Result<CubeMap, String> {
-        let load_image = |path| {
-            res.load_jpg(path)
-                .map_err(|e| format!("Error loading image {}: {:?}", path, e))
-                .unwrap()
-        };
+        let faces = load_faces(res, images_paths)?;
+        Ok(CubeMap::new(gl, faces, color_space))
+    }
+
+    /// Loads an equirectangular panorama and converts it to faces of `face_size` pixels.
+    pub fn load_panorama(
+        gl: &gl::Gl,
+        res: &ResourceLoader,
+        panorama_path: &str,
+        face_size: u32,
+        color_space: ColorSpace,
+    ) -> Result<CubeMap, String> {
+        let faces = load_panorama_faces(res, panorama_path, face_size)?;
+        Ok(CubeMap::new(gl, faces, color_space))
+    }
+
+    /// Loads the panorama again, keeping the current faces when it fails to load.
+    pub fn reload_panorama(
+        &self,
+        res: &ResourceLoader,
+        panorama_path: &str,
+        face_size: u32,
+    ) -> Result<(), String> {
+        let faces = load_panorama_faces(res, panorama_path, face_size)?;
+        self.replace_faces(&faces);
+
+        Ok(())
+    }
+
+    /// Loads the faces again, keeping the current ones when any of them fails to load.
+    pub fn reload(&self, res: &ResourceLoader, images_paths: [&str; 6]) -> Result<(), String> {
+        let faces = load_faces(res, images_paths)?;
+        self.replace_faces(&faces);
+
+        Ok(())
+    }
+
+    fn replace_faces(&self, faces: &CubeFaces) {
+        unsafe { self.gl.BindTexture(gl::TEXTURE_CUBE_MAP, self.id) };
+
+        match faces {
+            CubeFaces::Ldr(images) => {
+                let internal_format = match self.color_space {
+                    ColorSpace::Srgb => gl::SRGB8,
+                    ColorSpace::Linear => gl::RGB8,
+                };
+
+                for (index, image) in images.iter().enumerate() {
+                    self.upload_face(index, internal_format, image, gl::UNSIGNED_BYTE);
+                }
+            }
+            CubeFaces::Hdr(images) => {
+                // All faces share one format, so the largest value of any face decides it
+                let format = match images.iter().any(|image| {
+                    FloatFormat::fitting(image.as_raw(), false) == FloatFormat::Rgba32F
+                }) {
+                    true => FloatFormat::Rgba32F,
+                    false => FloatFormat::Rgb16F,
+                };
+
+                for (index, image) in images.iter().enumerate() {
+                    self.upload_face(index, format.to_gl(), image, gl::FLOAT);
+                }
+            }
+        }
+    }
+
+    fn upload_face<P: image::Pixel>(
+        &self,
+        index: usize,
+        internal_format: gl::types::GLenum,
+        image: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+        pixel_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            // RGB rows are tightly packed, so faces of any width need byte alignment
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            self.gl.TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + index as u32,
+                0,
+                internal_format as i32,
+                image.width() as i32,
+                image.height() as i32,
+                0,
+                gl::RGB,
+                pixel_type,
+                image.as_ptr().cast(),
+            );
+        }
+    }
+}
+
+impl Drop for CubeMap {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteTextures(1, [self.id].as_ptr()) };
+    }
+}
+
+/// Loads the faces as HDR images when any of them is a Radiance or OpenEXR file.
+fn load_faces(res: &ResourceLoader, images_paths: [&str; 6]) -> Result<CubeFaces, String> {
+    let load_error = |path: &str, e| format!("Error loading image {}: {}", path, e);
+
+    if images_paths.iter().any(|path| is_hdr_file(path)) {
+        let images = images_paths
+            .iter()
+            .map(|path| res.load_hdr(path).map_err(|e| load_error(path, e)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        return Ok(CubeFaces::Hdr(
+            images.try_into().expect("one image per face"),
+        ));
+    }
+
+    let images = images_paths
+        .iter()
+        .map(|path| res.load_jpg(path).map_err(|e| load_error(path, e)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(CubeFaces::Ldr(
+        images.try_into().expect("one image per face"),
+    ))
+}
+
+/// Loads the panorama as an HDR image when it is a Radiance or OpenEXR file.
+fn load_panorama_faces(
+    res: &ResourceLoader,
+    panorama_path: &str,
+    face_size: u32,
+) -> Result<CubeFaces, String> {
+    let panorama = match is_hdr_file(panorama_path) {
+        true => res.load_hdr(panorama_path).map(PanoramaImage::Hdr),
+        false => res.load_jpg(panorama_path).map(PanoramaImage::Ldr),
+    }
+    .map_err(|e| format!("Error loading image {}: {}", panorama_path, e))?;
+
+    CubeFaces::from_panorama(panorama, face_size)
+}
+
+/// Samples the panorama in the direction of every texel of the six faces.
+fn project_panorama(
+    panorama: &image::Rgb32FImage,
+    face_size: u32,
+) -> Result<[image::Rgb32FImage; 6], String> {
+    if panorama.width() == 0 || panorama.height() == 0 {
+        return Err("the panorama is empty".to_string());
+    }
+    if face_size == 0 {
+        return Err("the face size is zero".to_string());
+    }
+
+    Ok(std::array::from_fn(|face| {
+        image::ImageBuffer::from_fn(face_size, face_size, |x, y| {
+            // Texel center in -1..1, with the rows going down the face
+            let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+            let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+
+            // Face orientations of the OpenGL cube map specification
+            let direction = match face {
+                0 => Vector3::new(1.0, -t, -s),
+                1 => Vector3::new(-1.0, -t, s),
+                2 => Vector3::new(s, 1.0, t),
+                3 => Vector3::new(s, -1.0, -t),
+                4 => Vector3::new(s, -t, 1.0),
+                _ => Vector3::new(-s, -t, -1.0),
+            }
+            .normalize();
+
+            let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+            let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+
+            sample_panorama(panorama, u, v)
+        })
+    }))
+}
+
+/// Samples the panorama bilinearly at `u` and `v` in 0..1. The longitude wraps around, so
+/// texels next to the seam at ±π blend both edges, while the latitude clamps at the poles.
+fn sample_panorama(panorama: &image::Rgb32FImage, u: f32, v: f32) -> image::Rgb<f32> {
+    let (width, height) = panorama.dimensions();
+
+    // Pixel coordinates relative to the texel centers
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (fx, fy) = (x - x.floor(), y - y.floor());
+
+    let x0 = (x.floor() as i64).rem_euclid(width as i64) as u32;
+    let x1 = (x0 + 1) % width;
+    let y0 = y.floor() as u32;
+    let y1 = (y0 + 1).min(height - 1);
+
+    let lerp = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] {
+        std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+    };
+    let top = lerp(
+        panorama.get_pixel(x0, y0).0,
+        panorama.get_pixel(x1, y0).0,
+        fx,
+    );
+    let bottom = lerp(
+        panorama.get_pixel(x0, y1).0,
+        panorama.get_pixel(x1, y1).0,
+        fx,
+    );
+
+    image::Rgb(lerp(top, bottom, fy))
+}
+
+/// Whether the file is a floating point image, by its extension.
+fn is_hdr_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("hdr") || extension.eq_ignore_ascii_case("exr")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Panorama whose red channel is 1 in the first column and 0 elsewhere.
+    fn panorama(width: u32, height: u32) -> image::Rgb32FImage {
+        image::ImageBuffer::from_fn(width, height, |x, _| match x {
+            0 => image::Rgb([1.0, 0.0, 0.0]),
+            _ => image::Rgb([0.0, 0.0, 0.0]),
+        })
+    }
+
+    #[test]
+    fn samples_wrap_around_the_seam() {
+        let panorama = panorama(4, 2);
+
+        // The left and right edges both lie halfway between the first and last columns
+        assert_eq!(sample_panorama(&panorama, 0.0, 0.5).0[0], 0.5);
+        assert_eq!(sample_panorama(&panorama, 1.0, 0.5).0[0], 0.5);
+        assert_eq!(sample_panorama(&panorama, 0.125, 0.5).0[0], 1.0);
+        assert_eq!(sample_panorama(&panorama, 0.625, 0.5).0[0], 0.0);
+    }
+
+    #[test]
+    fn projects_the_seam_onto_the_negative_x_face() {
+        let faces = project_panorama(&panorama(4, 2), 1).unwrap();
+
+        // -X looks at the seam, +X at the middle of the panorama
+        assert_eq!(faces[1].get_pixel(0, 0).0[0], 0.5);
+        assert_eq!(faces[0].get_pixel(0, 0).0[0], 0.0);
+    }
 
-        let images = images_paths.map(load_image);
-        Ok(CubeMap::new(gl, images))
+    #[test]
+    fn rejects_empty_panoramas_and_faces() {
+        assert!(project_panorama(&panorama(0, 0), 16).is_err());
+        assert!(project_panorama(&panorama(4, 2), 0).is_err());
+        assert!(
+            CubeFaces::from_panorama(PanoramaImage::Ldr(image::RgbImage::new(0, 0)), 16).is_err()
+        );
     }
 }
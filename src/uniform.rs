@@ -17,11 +17,29 @@ impl UniformMat4f<'_> {
 
 impl<'a> Uniform for UniformMat4f<'a> {
     fn set(&self, gl: &gl::Gl, program_id: u32) {
-        let location = get_location(gl, program_id, self.name).unwrap();
+        let location = get_location(gl, program_id, self.name);
         unsafe { gl.UniformMatrix4fv(location, 1, gl::FALSE, self.value.as_ptr().cast()) };
     }
 }
 
+pub struct UniformMat3f<'a> {
+    name: &'a str,
+    value: cgmath::Matrix3<f32>,
+}
+
+impl UniformMat3f<'_> {
+    pub fn new(name: &str, value: cgmath::Matrix3<f32>) -> Box<UniformMat3f<'_>> {
+        Box::new(UniformMat3f { name, value })
+    }
+}
+
+impl<'a> Uniform for UniformMat3f<'a> {
+    fn set(&self, gl: &gl::Gl, program_id: u32) {
+        let location = get_location(gl, program_id, self.name);
+        unsafe { gl.UniformMatrix3fv(location, 1, gl::FALSE, self.value.as_ptr().cast()) };
+    }
+}
+
 pub struct Uniform3f<'a> {
     name: &'a str,
     value: (f32, f32, f32),
@@ -37,12 +55,67 @@ impl Uniform3f<'_> {
 }
 impl<'a> Uniform for Uniform3f<'a> {
     fn set(&self, gl: &gl::Gl, program_id: u32) {
-        let location = get_location(gl, program_id, self.name).unwrap();
+        let location = get_location(gl, program_id, self.name);
         let (v0, v1, v2) = self.value;
         unsafe { gl.Uniform3f(location, v0, v1, v2) };
     }
 }
 
+pub struct Uniform4f<'a> {
+    name: &'a str,
+    value: (f32, f32, f32, f32),
+}
+
+impl Uniform4f<'_> {
+    pub fn new<T: Into<(f32, f32, f32, f32)>>(name: &str, value: T) -> Box<Uniform4f<'_>> {
+        Box::new(Uniform4f {
+            name,
+            value: value.into(),
+        })
+    }
+}
+impl<'a> Uniform for Uniform4f<'a> {
+    fn set(&self, gl: &gl::Gl, program_id: u32) {
+        let location = get_location(gl, program_id, self.name);
+        let (v0, v1, v2, v3) = self.value;
+        unsafe { gl.Uniform4f(location, v0, v1, v2, v3) };
+    }
+}
+
+pub struct UniformFloat<'a> {
+    name: &'a str,
+    value: f32,
+}
+
+impl UniformFloat<'_> {
+    pub fn new(name: &str, value: f32) -> Box<UniformFloat<'_>> {
+        Box::new(UniformFloat { name, value })
+    }
+}
+impl<'a> Uniform for UniformFloat<'a> {
+    fn set(&self, gl: &gl::Gl, program_id: u32) {
+        let location = get_location(gl, program_id, self.name);
+        unsafe { gl.Uniform1f(location, self.value) };
+    }
+}
+
+pub struct UniformFloatArray<'a> {
+    name: &'a str,
+    value: Vec<f32>,
+}
+
+impl UniformFloatArray<'_> {
+    pub fn new(name: &str, value: Vec<f32>) -> Box<UniformFloatArray<'_>> {
+        Box::new(UniformFloatArray { name, value })
+    }
+}
+impl<'a> Uniform for UniformFloatArray<'a> {
+    fn set(&self, gl: &gl::Gl, program_id: u32) {
+        let location = get_location(gl, program_id, self.name);
+        unsafe { gl.Uniform1fv(location, self.value.len() as i32, self.value.as_ptr()) };
+    }
+}
+
 pub struct UniformInt<'a> {
     name: &'a str,
     value: i32,
@@ -55,26 +128,16 @@ impl UniformInt<'_> {
 }
 impl<'a> Uniform for UniformInt<'a> {
     fn set(&self, gl: &gl::Gl, program_id: u32) {
-        let location = get_location(gl, program_id, self.name).unwrap();
+        let location = get_location(gl, program_id, self.name);
         unsafe { gl.Uniform1i(location, self.value) };
     }
 }
 
-fn get_location(
-    gl: &gl::Gl,
-    program_id: u32,
-    uniform_name: &str,
-) -> Result<gl::types::GLint, String> {
+/// Looks up a uniform by name. Uniforms missing from the program, like ones the compiler
+/// optimized out or a reloaded shader removed, get location -1, which GL ignores when set.
+fn get_location(gl: &gl::Gl, program_id: u32, uniform_name: &str) -> gl::types::GLint {
     let uniform_cname =
         std::ffi::CString::new(uniform_name).expect("expected uniform name to have no nul bytes");
 
-    let location = unsafe { gl.GetUniformLocation(program_id, uniform_cname.as_ptr().cast()) };
-
-    match location {
-        -1 => Err(format!(
-            "Uniform location \"{}\" was not found in program with id {}",
-            uniform_name, program_id
-        )),
-        _ => Ok(location),
-    }
+    unsafe { gl.GetUniformLocation(program_id, uniform_cname.as_ptr().cast()) }
 }
@@ -3,6 +3,7 @@ use std::os::raw::c_void;
 pub struct UniformBufferObject<'a> {
     gl: gl::Gl,
     id: u32,
+    binding: u32,
     sub_uniforms: Vec<(&'a str, isize)>,
 }
 
@@ -32,10 +33,21 @@ impl<'a> UniformBufferObject<'a> {
         UniformBufferObject {
             gl: gl.clone(),
             id,
+            binding,
             sub_uniforms: sub_uniforms.to_vec(),
         }
     }
 
+    /// Binds the buffer to its binding point again, for when several buffers share it.
+    pub fn bind(&self) {
+        let total_size = self.sub_uniforms.iter().map(|(_, size)| size).sum();
+
+        unsafe {
+            self.gl
+                .BindBufferRange(gl::UNIFORM_BUFFER, self.binding, self.id, 0, total_size)
+        };
+    }
+
     pub fn write_sub_data(&self, uniform: &str, data: *const c_void) {
         let mut offset = 0;
 